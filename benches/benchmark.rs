@@ -4,7 +4,7 @@ extern crate art_rs;
 
 use art_rs::Art;
 use criterion::Criterion;
-use criterion::{black_box, BatchSize, Benchmark};
+use criterion::BatchSize;
 use radix_trie::Trie;
 use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
@@ -30,7 +30,7 @@ fn insert_art() {
     let input = File::open(PATH).unwrap();
     let input = BufReader::new(input);
     for line in input.lines() {
-        let st = line.unwrap().as_bytes().to_vec();
+        let st = line.unwrap().into_bytes();
         map.insert(st.clone(), st);
     }
 }
@@ -46,18 +46,18 @@ fn insert_hash_map() {
 }
 
 fn insert_radix_trie_b(c: &mut Criterion) {
-    c.bench_function("insert_radix_trie", |b| b.iter(|| insert_radix_trie()));
+    c.bench_function("insert_radix_trie", |b| b.iter(insert_radix_trie));
 }
 
 fn insert_art_b(c: &mut Criterion) {
-    c.bench_function("insert_art", |b| b.iter(|| insert_art()));
+    c.bench_function("insert_art", |b| b.iter(insert_art));
 }
 
 fn insert_hash_map_b(c: &mut Criterion) {
-    c.bench_function("insert_simple_hashmap", |b| b.iter(|| insert_hash_map()));
+    c.bench_function("insert_simple_hashmap", |b| b.iter(insert_hash_map));
 }
 
-fn search_simple_trie(map: Rc<Art>) {
+fn search_simple_trie(map: Rc<Art<Vec<u8>, Vec<u8>>>) {
     let input = File::open(PATH).unwrap();
     let input = BufReader::new(input);
     for (index, line) in input.lines().enumerate() {
@@ -65,8 +65,7 @@ fn search_simple_trie(map: Rc<Art>) {
             break;
         }
         let st = line.unwrap();
-        let st = st.as_bytes().to_vec();
-        map.search(&st);
+        map.search(st.as_str());
     }
 }
 
@@ -75,8 +74,7 @@ fn search_art_b(c: &mut Criterion) {
     let input = File::open(PATH).unwrap();
     let input = BufReader::new(input);
     for line in input.lines() {
-        let st = line.unwrap();
-        let st = st.as_bytes().to_vec();
+        let st = line.unwrap().into_bytes();
         map.insert(st.clone(), st);
     }
     let map = Rc::new(map);
@@ -84,7 +82,7 @@ fn search_art_b(c: &mut Criterion) {
     c.bench_function("search_art", move |b| {
         b.iter_batched(
             || map.clone(),
-            |map| search_simple_trie(map),
+            search_simple_trie,
             BatchSize::LargeInput,
         )
     });
@@ -115,7 +113,7 @@ fn search_radix_trie_b(c: &mut Criterion) {
     c.bench_function("search_radix", move |b| {
         b.iter_batched(
             || map.clone(),
-            |map| search_radix_trie(map),
+            search_radix_trie,
             BatchSize::LargeInput,
         )
     });
@@ -145,32 +143,31 @@ fn search_hash_map_b(c: &mut Criterion) {
     c.bench_function("search_hashmap", move |b| {
         b.iter_batched(
             || map.clone(),
-            |map| search_hash_map(map),
+            search_hash_map,
             BatchSize::LargeInput,
         )
     });
 }
 
-fn search_integer_simple_trie(map: Rc<Art>) {
+fn search_integer_simple_trie(map: Rc<Art<i32, i32>>) {
     let input = File::open(PATH_RANDOM_NOS).unwrap();
     let input = BufReader::new(input);
     for line in input.lines() {
-        let val= line.unwrap();
-        map.search(&val.as_bytes());
+        let val: i32 = line.unwrap().parse().unwrap();
+        map.search(&val);
     }
 }
 
 fn search_art_integers_b(c: &mut Criterion) {
     let mut map = Art::new();
     for i in 0..1000000 {
-        let st = format!("{}", i).as_bytes().to_vec();
-        map.insert(st.clone(), st);
+        map.insert(i, i);
     }
     let map = Rc::new(map);
     c.bench_function("search_integer_art", move |b| {
         b.iter_batched(
             || map.clone(),
-            |map| search_integer_simple_trie(map),
+            search_integer_simple_trie,
             BatchSize::LargeInput,
         )
     });
@@ -194,7 +191,7 @@ fn search_hash_map_integers_b(c: &mut Criterion) {
     c.bench_function("search_integer_hashmap", move |b| {
         b.iter_batched(
             || map.clone(),
-            |map| search_integer_hash_map(map),
+            search_integer_hash_map,
             BatchSize::LargeInput,
         )
     });
@@ -1,17 +1,135 @@
-use std::borrow::{Borrow, BorrowMut};
-use std::cmp::min;
+use std::borrow::{Borrow, BorrowMut, Cow};
+use std::cmp::{min, Ordering};
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::iter::FromIterator;
+use std::marker::PhantomData;
 use std::mem::replace;
-use std::ops::DerefMut;
+use std::ops::{Bound, Index, RangeBounds};
 
 use xi_rope::compare::ne_idx;
 
-use crate::{Art, Leaf, Node, Node16, Node4, NodeMeta, MAX_PREFIX};
+use crate::{
+    Art, ArtError, ArtKey, Automaton, AutomatonSearch, Cursor, CursorMut, Difference, Entry,
+    FuzzySearch, Glob, Intersection, IntoIter, Iter, IterMut, KeyLookup, KeyPrefix, Keys, Leaf,
+    Lockstep, Node, Node4, OccupiedEntry, Prefixes, Structure, SymmetricDifference, Union,
+    VacantEntry, Values, MAX_PREFIX,
+};
 
-impl Art {
+impl<K: ArtKey, V> Default for Art<K, V> {
+    fn default() -> Self {
+        Art::new()
+    }
+}
+
+impl<K: ArtKey, V> FromIterator<(K, V)> for Art<K, V> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut art = Art::new();
+        art.extend(iter);
+        art
+    }
+}
+
+impl<K: ArtKey, V> Extend<(K, V)> for Art<K, V> {
+    fn extend<I: IntoIterator<Item = (K, V)>>(&mut self, iter: I) {
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl<K: ArtKey, V> IntoIterator for Art<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter::new(self.root)
+    }
+}
+
+impl<'a, K: ArtKey, V> IntoIterator for &'a Art<K, V> {
+    type Item = (&'a [u8], &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K: ArtKey, V> IntoIterator for &'a mut Art<K, V> {
+    type Item = (&'a [u8], &'a mut V);
+    type IntoIter = IterMut<'a, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+/// Panics when the key isn't in the tree.
+impl<K: ArtKey, V, Q: KeyLookup<K> + ?Sized> Index<&Q> for Art<K, V> {
+    type Output = V;
+
+    fn index(&self, key: &Q) -> &V {
+        self.search(key).expect("key not found in the tree")
+    }
+}
+
+// maps are compared by their entries in key order, however their nodes are laid out
+impl<K: ArtKey, V: PartialEq> PartialEq for Art<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl<K: ArtKey, V: Eq> Eq for Art<K, V> {}
+
+impl<K: ArtKey, V: PartialOrd> PartialOrd for Art<K, V> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.iter().partial_cmp(other.iter())
+    }
+}
+
+impl<K: ArtKey, V: Ord> Ord for Art<K, V> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.iter().cmp(other.iter())
+    }
+}
+
+impl<K: ArtKey, V: Hash> Hash for Art<K, V> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len());
+        for entry in self.iter() {
+            entry.hash(state);
+        }
+    }
+}
+
+/// Prints the entries as a map, with the keys decoded back into `K` where they can be.
+impl<K: ArtKey + Debug, V: Debug> Debug for Art<K, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut map = f.debug_map();
+        for (key, value) in self.iter() {
+            match K::decode(key) {
+                Some(decoded) => map.entry(&decoded, value),
+                None => map.entry(&key, value),
+            };
+        }
+        map.finish()
+    }
+}
+
+impl<'a, V: Debug> Debug for Structure<'a, V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.root.fmt(f)
+    }
+}
+
+impl<K: ArtKey, V> Art<K, V> {
     pub fn new() -> Self {
         Art {
             root: Node::None,
             size: 0,
+            marker: PhantomData,
         }
     }
 
@@ -23,6 +141,96 @@ impl Art {
         self.size == 0
     }
 
+    /// Returns an iterator over the entries in lexicographic key order.
+    pub fn iter(&self) -> Iter<'_, K, V> {
+        Iter::new(&self.root)
+    }
+
+    /// Returns an iterator over the entries in lexicographic key order, with mutable references
+    /// to the values.
+    pub fn iter_mut(&mut self) -> IterMut<'_, V> {
+        IterMut::new(&mut self.root)
+    }
+
+    /// Returns an iterator over the keys in lexicographic order.
+    pub fn keys(&self) -> Keys<'_, K, V> {
+        Keys { inner: self.iter() }
+    }
+
+    /// Returns an iterator over the values in key order.
+    pub fn values(&self) -> Values<'_, K, V> {
+        Values { inner: self.iter() }
+    }
+
+    /// Returns an iterator over the entries whose encoded key starts with the encoding of
+    /// `prefix`, in key order.
+    pub fn prefix_iter<Q: KeyPrefix<K> + ?Sized>(&self, prefix: &Q) -> Iter<'_, K, V> {
+        let prefix = prefix.encode();
+        let prefix: &[u8] = &prefix;
+        let mut current = &self.root;
+        let mut depth = 0;
+        loop {
+            match current {
+                Node::None => return Iter::empty(),
+                Node::Leaf(leaf) => {
+                    if leaf.key.starts_with(prefix) {
+                        return Iter::new(current);
+                    }
+                    return Iter::empty();
+                }
+                _ => {
+                    let remaining = prefix.len() - depth;
+                    let matched = current.prefix_match_deep(prefix, depth);
+
+                    // the prefix ends inside the compressed path of this node, every key below
+                    // starts with it as long as the path matched so far
+                    if remaining <= current.prefix_len() {
+                        if matched < remaining {
+                            return Iter::empty();
+                        }
+                        return Iter::new(current);
+                    }
+
+                    if matched < current.prefix_len() {
+                        return Iter::empty();
+                    }
+                    depth += current.prefix_len();
+
+                    match current.find_child(prefix, depth) {
+                        Some(child) => {
+                            current = child;
+                            depth += 1;
+                        }
+                        None => return Iter::empty(),
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns an iterator over the entries whose key falls in `range`, in key order.
+    pub fn range<Q, R>(&self, range: R) -> Iter<'_, K, V>
+    where
+        Q: KeyLookup<K> + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        let start = Self::encode_bound(range.start_bound());
+        let end = Self::encode_bound(range.end_bound());
+        Iter::range(
+            &self.root,
+            start.as_ref().map(|key| key.as_ref()),
+            end.as_ref().map(|key| key.as_ref()),
+        )
+    }
+
+    fn encode_bound<Q: ArtKey + ?Sized>(bound: Bound<&Q>) -> Bound<Cow<'_, [u8]>> {
+        match bound {
+            Bound::Included(key) => Bound::Included(key.encode()),
+            Bound::Excluded(key) => Bound::Excluded(key.encode()),
+            Bound::Unbounded => Bound::Unbounded,
+        }
+    }
+
     fn equals(one: &[u8], two: &[u8]) -> bool {
         if one.len() != two.len() {
             false
@@ -31,31 +239,84 @@ impl Art {
             // TODO enable after benchmarking
             // use simd to compare..
             let res = ne_idx(one, two);
-            match res {
-                Some(_) => false,
-                None => true,
-            }
+            res.is_none()
+        }
+    }
+
+    /// Returns the keys in `self` or `other`, in order.
+    pub fn union<'a, W>(&'a self, other: &'a Art<K, W>) -> Union<'a, K, V, W> {
+        Union {
+            inner: Lockstep::new(&self.root, &other.root),
+        }
+    }
+
+    /// Returns the keys in both `self` and `other`, in order.
+    ///
+    /// Both trees are walked in lockstep, each one skipping ahead to the other's next key, so
+    /// the subtrees whose compressed paths diverge from the other tree are passed over whole.
+    pub fn intersection<'a, W>(&'a self, other: &'a Art<K, W>) -> Intersection<'a, K, V, W> {
+        Intersection {
+            inner: Lockstep::new(&self.root, &other.root),
+        }
+    }
+
+    /// Returns the keys in `self` but not in `other`, in order.
+    ///
+    /// `other` skips ahead to the next key of `self` like in `intersection`.
+    pub fn difference<'a, W>(&'a self, other: &'a Art<K, W>) -> Difference<'a, K, V, W> {
+        Difference {
+            inner: Lockstep::new(&self.root, &other.root),
+        }
+    }
+
+    /// Returns the keys in exactly one of `self` and `other`, in order.
+    pub fn symmetric_difference<'a, W>(
+        &'a self,
+        other: &'a Art<K, W>,
+    ) -> SymmetricDifference<'a, K, V, W> {
+        SymmetricDifference {
+            inner: Lockstep::new(&self.root, &other.root),
         }
     }
 
-    pub fn search(&self, key: &[u8]) -> Option<&[u8]> {
-        let mut stack: Vec<&Node> = Vec::new();
+    /// Returns the nodes of the tree, for dumping how the entries are laid out with `{:#?}`.
+    pub fn structure(&self) -> Structure<'_, V> {
+        Structure { root: &self.root }
+    }
+
+    /// Returns an unpositioned cursor over the tree, call one of its seek methods to place it.
+    ///
+    /// Cursors hand out the encoded keys.
+    pub fn cursor(&self) -> Cursor<'_, K, V> {
+        Cursor::new(&self.root)
+    }
+
+    /// Returns an unpositioned cursor that can modify the tree as it walks it.
+    pub fn cursor_mut(&mut self) -> CursorMut<'_, K, V> {
+        CursorMut::new(&mut self.root, &mut self.size)
+    }
+
+    /// Returns the value stored under `key`.
+    ///
+    /// The key can be borrowed in any form `KeyLookup` admits for the key type, e.g. a `str`,
+    /// a byte slice or a byte array for string keys, without allocating.
+    pub fn search<Q: KeyLookup<K> + ?Sized>(&self, key: &Q) -> Option<&V> {
+        let key = key.encode();
+        let key: &[u8] = &key;
+        let mut stack: Vec<&Node<V>> = Vec::new();
         stack.push(self.root.borrow());
         let mut depth: usize = 0;
-        loop {
-            let current = match stack.pop() {
-                Some(item) => item,
-                None => break,
-            };
-
+        while let Some(current) = stack.pop() {
             match current {
                 Node::Leaf(leaf) => {
-                    if Art::equals(leaf.key.as_slice(), key) {
+                    if Self::equals(leaf.key.as_slice(), key) {
                         return Some(&leaf.value);
                     } else {
                         break;
                     }
                 }
+                // empty tree, everything has been removed
+                Node::None => break,
                 _ => {}
             }
 
@@ -86,49 +347,324 @@ impl Art {
         None
     }
 
-    pub fn insert(&mut self, key: Vec<u8>, value: Vec<u8>) {
+    /// Returns the entry with the longest key that is a prefix of `key`, `key` itself
+    /// included, as routing tables look up the most specific route for an address.
+    ///
+    /// The prefixes are encoded keys, so they only line up with the keys themselves for
+    /// encodings without a terminator, like those of strings and byte strings.
+    pub fn longest_prefix_match<Q: KeyLookup<K> + ?Sized>(&self, key: &Q) -> Option<(&[u8], &V)> {
+        self.prefixes_of(key).last()
+    }
+
+    /// Returns an iterator over the entries whose key is a prefix of `key`, from the shortest
+    /// key to the longest. They are found in the one descent a lookup of `key` makes.
+    pub fn prefixes_of<'k, Q: KeyLookup<K> + ?Sized>(&self, key: &'k Q) -> Prefixes<'_, 'k, V> {
+        Prefixes::new(&self.root, key.encode())
+    }
+
+    /// Returns an iterator over the entries whose key is at most `max_edits` insertions,
+    /// deletions or substitutions of a byte away from `query`, in key order along with their
+    /// distance.
+    ///
+    /// The distance is kept for every prefix of the query as the tree is walked down, so a
+    /// subtree is skipped, compressed path and all, as soon as none of its keys can be close
+    /// enough. The edits are counted on the encoded keys, in bytes rather than characters.
+    pub fn fuzzy_search<Q: KeyLookup<K> + ?Sized>(
+        &self,
+        query: &Q,
+        max_edits: usize,
+    ) -> FuzzySearch<'_, V> {
+        FuzzySearch::new(&self.root, query.encode().into_owned(), max_edits)
+    }
+
+    /// Returns an iterator over the entries whose key matches a glob `pattern`, in key order.
+    ///
+    /// `?` matches any byte and `*` any run of bytes, `[...]` one byte out of a class of bytes
+    /// and ranges like `[a-z0-9_]`, or out of every other byte with `[!...]` or `[^...]`.
+    /// A `\` makes the byte after it match only itself. The pattern is matched against the
+    /// encoded keys byte by byte, the tree is only walked down the bytes it can match.
+    pub fn glob<P: AsRef<[u8]> + ?Sized>(&self, pattern: &P) -> Glob<'_, V> {
+        Glob::new(&self.root, pattern.as_ref())
+    }
+
+    /// Returns an iterator over the entries whose encoded key `automaton` accepts, in key
+    /// order.
+    ///
+    /// The automaton is run down the tree a node at a time, its state shared by every key
+    /// below, and a subtree is skipped as soon as the automaton can't match anymore.
+    pub fn search_automaton<A: Automaton>(&self, automaton: A) -> AutomatonSearch<'_, A, V> {
+        AutomatonSearch::new(&self.root, automaton)
+    }
+
+    /// Returns `true` if a value is stored under `key`.
+    pub fn contains_key<Q: KeyLookup<K> + ?Sized>(&self, key: &Q) -> bool {
+        self.search(key).is_some()
+    }
+
+    /// Returns a mutable reference to the value stored under `key`.
+    pub fn get_mut<Q: KeyLookup<K> + ?Sized>(&mut self, key: &Q) -> Option<&mut V> {
+        let key = key.encode();
+        let key: &[u8] = &key;
+        let mut current = &mut self.root;
+        let mut depth = 0;
+        loop {
+            match current {
+                Node::Leaf(leaf) => {
+                    if !Self::equals(leaf.key.as_slice(), key) {
+                        return None;
+                    }
+                    return Node::value_mut(Some(current)).ok();
+                }
+                Node::None => return None,
+                _ => {}
+            }
+
+            if current.prefix_len() > 0 {
+                let prefix_len = current.prefix_match(key, depth);
+                // prefix does not match, stop
+                if prefix_len
+                    != min(
+                        min(MAX_PREFIX, current.prefix_len()),
+                        current.partial().len(),
+                    )
+                {
+                    return None;
+                }
+                depth += current.prefix_len();
+            }
+
+            current = current.find_child_mut(key, depth)?;
+            depth += 1;
+        }
+    }
+
+    /// Inserts a key-value pair, returning the value previously stored under the key.
+    ///
+    /// Panics where `try_insert` reports an error.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.try_insert(key, value)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Inserts a key-value pair, returning the value previously stored under the key, or the
+    /// error that kept it from being inserted.
+    pub fn try_insert(&mut self, key: K, value: V) -> Result<Option<V>, ArtError> {
+        let key = key.into_encoded();
+        let (_, old_value) = Node::insert_at(&mut self.root, 0, key, value)?;
+        if old_value.is_none() {
+            self.size += 1;
+        }
+        Ok(old_value)
+    }
+
+    /// Gets the entry for `key` for in-place manipulation, descending the tree only once.
+    ///
+    /// Panics where `try_entry` reports an error.
+    pub fn entry(&mut self, key: K) -> Entry<'_, K, V> {
+        self.try_entry(key).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Gets the entry for `key` for in-place manipulation, descending the tree only once, or
+    /// returns the error that kept the descent from finishing.
+    pub fn try_entry(&mut self, key: K) -> Result<Entry<'_, K, V>, ArtError> {
+        enum Step {
+            Vacant,
+            Occupied(Option<Option<u8>>),
+            Descend(usize),
+        }
+
+        let encoded = key.encode();
+        let mut current = &mut self.root;
+        let mut depth = 0;
+        loop {
+            let step = match current {
+                Node::None => Step::Vacant,
+                Node::Leaf(leaf) => {
+                    // only a root leaf can match here, the others are checked from their parent
+                    if leaf.key[..] == encoded[..] {
+                        Step::Occupied(None)
+                    } else {
+                        Step::Vacant
+                    }
+                }
+                _ => {
+                    if current.prefix_match_deep(&encoded, depth) < current.prefix_len() {
+                        // the key branches off inside the prefix, which has to be split
+                        Step::Vacant
+                    } else {
+                        let child_depth = depth + current.prefix_len();
+                        match current.find_child(&encoded, child_depth) {
+                            Some(Node::Leaf(leaf)) if leaf.key[..] == encoded[..] => {
+                                Step::Occupied(Some(encoded.get(child_depth).copied()))
+                            }
+                            Some(_) => Step::Descend(child_depth),
+                            None => Step::Vacant,
+                        }
+                    }
+                }
+            };
+
+            match step {
+                Step::Vacant => {
+                    let encoded = match encoded {
+                        Cow::Owned(encoded) => Some(encoded),
+                        Cow::Borrowed(_) => None,
+                    };
+                    return Ok(Entry::Vacant(VacantEntry {
+                        key,
+                        encoded,
+                        node: current,
+                        depth,
+                        size: &mut self.size,
+                    }));
+                }
+                Step::Occupied(key_char) => {
+                    drop(encoded);
+                    return Ok(Entry::Occupied(OccupiedEntry {
+                        key,
+                        node: current,
+                        key_char,
+                        size: &mut self.size,
+                    }));
+                }
+                Step::Descend(child_depth) => {
+                    current = current
+                        .find_child_mut(&encoded, child_depth)
+                        .ok_or(ArtError::Corrupted("child went missing during entry"))?;
+                    depth = child_depth + 1;
+                }
+            }
+        }
+    }
+
+    /// Removes a key, returning the value stored under it.
+    ///
+    /// Panics where `try_remove` reports an error.
+    pub fn remove<Q: KeyLookup<K> + ?Sized>(&mut self, key: &Q) -> Option<V> {
+        self.try_remove(key).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Removes a key, returning the value stored under it, or the error that kept it from
+    /// being removed.
+    pub fn try_remove<Q: KeyLookup<K> + ?Sized>(&mut self, key: &Q) -> Result<Option<V>, ArtError> {
+        let key = key.encode();
+        let key: &[u8] = &key;
         let mut current = self.root.borrow_mut();
         let mut depth = 0;
-        let mut count = 0;
+
+        let removed = loop {
+            match current {
+                Node::None => break None,
+                Node::Leaf(leaf) => {
+                    // only reachable when the root itself is a leaf
+                    if !Self::equals(leaf.key.as_slice(), key) {
+                        break None;
+                    }
+                    break Some(replace(current, Node::None));
+                }
+                _ => {
+                    if current.prefix_len() > 0 {
+                        let prefix_len = current.prefix_match(key, depth);
+                        // prefix does not match, key is not present
+                        if prefix_len
+                            != min(
+                                min(MAX_PREFIX, current.prefix_len()),
+                                current.partial().len(),
+                            )
+                        {
+                            break None;
+                        }
+                        depth += current.prefix_len();
+                    }
+
+                    // stop at the parent of the leaf so that it can drop the child and shrink
+                    let leaf_found = match current.find_child(key, depth) {
+                        Some(Node::Leaf(leaf)) => {
+                            if !Self::equals(leaf.key.as_slice(), key) {
+                                break None;
+                            }
+                            true
+                        }
+                        Some(_) => false,
+                        None => break None,
+                    };
+
+                    if leaf_found {
+                        break current.remove_child(key.get(depth).copied())?;
+                    }
+                    current = current
+                        .find_child_mut(key, depth)
+                        .ok_or(ArtError::Corrupted("child went missing during remove"))?;
+                    depth += 1;
+                }
+            }
+        };
+
+        match removed {
+            Some(Node::Leaf(leaf)) => {
+                self.size = self
+                    .size
+                    .checked_sub(1)
+                    .ok_or(ArtError::Corrupted("size is out of sync with the leaves"))?;
+                Ok(Some(leaf.value))
+            }
+            Some(_) => Err(ArtError::Corrupted(
+                "removed an inner node in place of a leaf",
+            )),
+            None => Ok(None),
+        }
+    }
+}
+
+// the insertion engine shared by Art, CursorMut and the entries, which only deal in encoded
+// keys
+impl<V> Node<V> {
+    // insert below node, whose compressed path starts at depth, handing back the stored value
+    // along with the one it replaced
+    pub(crate) fn insert_at(
+        node: &mut Node<V>,
+        depth: usize,
+        key: Vec<u8>,
+        value: V,
+    ) -> Result<(&mut V, Option<V>), ArtError> {
+        let mut current = node;
+        let mut depth = depth;
 
         loop {
             match current {
                 Node::None => {
                     let leaf = Node::Leaf(Leaf::new(key, value));
-                    self.root = leaf;
-                    count += 1;
-                    break;
+                    *current = leaf;
+                    return Ok((Self::value_mut(Some(current))?, None));
                 }
                 Node::Leaf(leaf) => {
                     // replace value if the key is same
                     if leaf.key.eq(&key) {
-                        leaf.value = value;
-                        break;
+                        let old_value = replace(&mut leaf.value, value);
+                        return Ok((Self::value_mut(Some(current))?, Some(old_value)));
                     }
 
                     // upgrade the leaf to Node4
-                    let mut node4 = Node4::new();
+                    let mut node4 = Node4::try_new()?;
 
                     // compute prefix
-                    let prefix_len = Art::longest_common_prefix(&leaf.key, &key, depth);
-                    node4.meta.partial = Art::calculate_partial(&key, depth, prefix_len);
+                    let prefix_len = Self::longest_common_prefix(&leaf.key, &key, depth);
+                    node4.meta.partial = Self::calculate_partial(&key, depth, prefix_len);
                     node4.meta.prefix_len = prefix_len;
                     // push the depth by prefix len
-                    depth = depth + prefix_len;
+                    depth += prefix_len;
 
                     // add the leaves to the new node 4
-
-                    let leaf = leaf.clone();
-                    let key_char = Node::key_char(&leaf.key, depth);
-                    node4.add_child(Node::Leaf(leaf), key_char);
+                    let key_char = Node::<V>::key_char(&leaf.key, depth);
+                    let leaf = replace(&mut *current, Node::None);
+                    node4.add_child(leaf, key_char)?;
 
                     let leaf2 = Leaf::new(key, value);
-                    let key_char = Node::key_char(&leaf2.key, depth);
-                    node4.add_child(Node::Leaf(leaf2), key_char);
+                    let key_char = Node::<V>::key_char(&leaf2.key, depth);
+                    node4.add_child(Node::Leaf(leaf2), key_char)?;
 
                     *current = Node::Node4(node4);
-                    count += 1;
-                    break;
+                    return Ok((Self::value_mut(current.child_mut(key_char))?, None));
                 }
                 _ => {
                     let current_prefix_len = current.prefix_match_deep(&key, depth);
@@ -144,76 +680,75 @@ impl Art {
                         if !current.child_exists(&key, depth) {
                             let key_char = key.get(depth).copied();
                             let leaf = Node::Leaf(Leaf::new(key, value));
-                            current.add_child(leaf, key_char);
-                            count += 1;
-                            break;
+                            current.add_child(leaf, key_char)?;
+                            return Ok((Self::value_mut(current.child_mut(key_char))?, None));
                         }
-                        current = current.find_child_mut(&key, depth).unwrap();
+                        current = current
+                            .find_child_mut(&key, depth)
+                            .ok_or(ArtError::Corrupted("child went missing during insert"))?;
                         depth += 1;
                         continue;
                     }
 
                     // create a new node to split at current_prefix_len
-                    let mut node4 = Node4::new();
+                    let mut node4 = Node4::try_new()?;
                     node4.meta.prefix_len = current_prefix_len;
-                    node4.meta.partial = current.partial()[..min(current_prefix_len, MAX_PREFIX)]
-                        .iter()
-                        .copied()
-                        .collect();
+                    node4.meta.partial =
+                        current.partial()[..min(current_prefix_len, MAX_PREFIX)].to_vec();
 
                     let old_prefix_len = current.prefix_len();
                     // fix up current node
                     let new_prefix_len = current.prefix_len() - (current_prefix_len + 1);
-                    current.set_prefix_len(new_prefix_len);
 
-                    if old_prefix_len <= MAX_PREFIX {
+                    // the byte the current node hangs off in the new node and what is left of
+                    // its prefix, worked out before touching the node so that a corrupted
+                    // node is left as it was
+                    let (key_char, new_partial) = if old_prefix_len <= MAX_PREFIX {
                         let new_partial = current
                             .partial()
                             .iter()
                             .skip(current_prefix_len + 1)
                             .copied()
-                            .take(min(current.prefix_len(), MAX_PREFIX))
+                            .take(min(new_prefix_len, MAX_PREFIX))
                             .collect();
-                        // extract the key char before munging the partial
-                        let key_char = current.partial()[current_prefix_len];
-                        current.set_partial(new_partial);
-
-                        // place old current as a child under
-                        let old_node = replace(&mut *current, Node::Node4(node4));
-                        current.add_child(old_node, Some(key_char));
+                        (current.partial()[current_prefix_len], new_partial)
                     } else {
-                        let leaf = current.minimum();
-                        let (key_char, new_partial) = if let Node::Leaf(leaf) = leaf {
-                            let new_partial: Vec<u8> = leaf
-                                .key
-                                .iter()
-                                .skip(depth + current_prefix_len + 1)
-                                .take(min(current.prefix_len(), MAX_PREFIX))
-                                .map(|e| *e)
-                                .collect();
-                            let key_char = leaf.key[depth + current_prefix_len];
-                            (key_char, new_partial)
-                        } else {
-                            panic!("Should not be here");
-                        };
-                        current.set_partial(new_partial);
-                        // place old current as a child under
-                        let old_node = replace(&mut *current, Node::Node4(node4));
-                        current.add_child(old_node, Some(key_char));
-                    }
+                        let leaf = current
+                            .minimum()
+                            .ok_or(ArtError::Corrupted("inner node without any leaves"))?;
+                        let new_partial: Vec<u8> = leaf
+                            .key
+                            .iter()
+                            .skip(depth + current_prefix_len + 1)
+                            .take(min(new_prefix_len, MAX_PREFIX))
+                            .copied()
+                            .collect();
+                        (leaf.key[depth + current_prefix_len], new_partial)
+                    };
+                    current.set_prefix_len(new_prefix_len)?;
+                    current.set_partial(new_partial)?;
 
-                    let key_char = key[depth + current_prefix_len];
-                    current.add_child(Node::Leaf(Leaf::new(key, value)), Some(key_char));
-                    count += 1;
-                    break;
+                    // place old current as a child under
+                    let old_node = replace(&mut *current, Node::Node4(node4));
+                    current.add_child(old_node, Some(key_char))?;
+
+                    // the key may end inside the prefix, it then becomes the term leaf
+                    let key_char = Node::<V>::key_char(&key, depth + current_prefix_len);
+                    current.add_child(Node::Leaf(Leaf::new(key, value)), key_char)?;
+                    return Ok((Self::value_mut(current.child_mut(key_char))?, None));
                 }
             }
         }
+    }
 
-        self.size += count;
+    pub(crate) fn value_mut(node: Option<&mut Node<V>>) -> Result<&mut V, ArtError> {
+        match node {
+            Some(Node::Leaf(leaf)) => Ok(&mut leaf.value),
+            _ => Err(ArtError::Corrupted("expected a leaf")),
+        }
     }
 
-    fn calculate_partial(key: &Vec<u8>, depth: usize, prefix_len: usize) -> Vec<u8> {
+    fn calculate_partial(key: &[u8], depth: usize, prefix_len: usize) -> Vec<u8> {
         let mut partial: Vec<u8> = Vec::new();
         let max_partial = min(prefix_len, MAX_PREFIX);
 
@@ -226,13 +761,12 @@ impl Art {
         partial
     }
 
-    fn longest_common_prefix(key1: &Vec<u8>, key2: &Vec<u8>, depth: usize) -> usize {
+    fn longest_common_prefix(key1: &[u8], key2: &[u8], depth: usize) -> usize {
         // TODO simd this
         let max_compare = min(key1.len(), key2.len());
         let mut prefix_len = depth;
 
         for i in depth..max_compare {
-            let i = i as usize;
             if key1[i] != key2[i] {
                 break;
             }
@@ -245,22 +779,24 @@ impl Art {
 //
 #[cfg(test)]
 mod tests {
-    use std::collections::VecDeque;
-    use std::fs::{read, File};
+    use std::collections::hash_map::DefaultHasher;
+    use std::collections::BTreeMap;
+    use std::fmt::Debug;
+    use std::fs::File;
     use std::io::{BufRead, BufReader};
 
-    use crate::{Art, Leaf, Node, Node16, Node4, NodeMeta, MAX_PREFIX};
+    use crate::{Art, ArtError, Leaf, Node, Node4, MAX_PREFIX};
 
     use super::*;
 
-    fn _insert(art: &mut Art, items: &Vec<&str>) {
+    fn _insert(art: &mut Art<Vec<u8>, Vec<u8>>, items: &Vec<&str>) {
         items.iter().for_each(|item| {
             art.insert(Vec::from(item.as_bytes()), Vec::from(item.as_bytes()));
             println!("{}", "=".repeat(10));
         });
     }
 
-    fn _verify_children(node: &Node4, elems_to_match: Vec<u8>) {
+    fn _verify_children<V>(node: &Node4<V>, elems_to_match: Vec<u8>) {
         let keys: Vec<u8> = node
             .children()
             .iter()
@@ -269,7 +805,11 @@ mod tests {
         assert_eq!(keys, elems_to_match);
     }
 
-    fn _insert_with_key_fn(art: &mut Art, items: &Vec<&str>, key_fn: fn(u8) -> u8) {
+    fn _insert_with_key_fn(
+        art: &mut Art<Vec<u8>, Vec<u8>>,
+        items: &Vec<&str>,
+        key_fn: fn(u8) -> u8,
+    ) {
         items.iter().for_each(|item| {
             let x: Vec<u8> = item.as_bytes().iter().map(|x| key_fn(*x)).collect();
             art.insert(Vec::from(item.as_bytes()), x);
@@ -277,7 +817,7 @@ mod tests {
         });
     }
 
-    fn print_art(art: &Art) {
+    fn print_art<V: Debug>(art: &Art<Vec<u8>, V>) {
         let to_string = |digits: &Vec<u8>| -> String {
             let mut buffer = String::new();
             digits.iter().for_each(|c| {
@@ -289,19 +829,13 @@ mod tests {
 
         // dump tree
         let mut stack = Vec::new();
-        stack.push((0 as i8, art.root.borrow()));
+        stack.push((0_i8, art.root.borrow()));
         let mut indent = 0;
-        loop {
-            let (current_char, current) = match stack.pop() {
-                Some(item) => {
-                    if item.0 == -1 {
-                        indent -= 5;
-                        continue;
-                    }
-                    item
-                }
-                None => break,
-            };
+        while let Some((current_char, current)) = stack.pop() {
+            if current_char == -1 {
+                indent -= 5;
+                continue;
+            }
 
             match current {
                 Node::Leaf(leaf) => {
@@ -310,7 +844,7 @@ mod tests {
                         indent = indent,
                         tag = "",
                         key = to_string(&leaf.key),
-                        val = to_string(&leaf.value)
+                        val = &leaf.value
                     );
                     continue;
                 }
@@ -366,8 +900,8 @@ mod tests {
         let items = vec!["A"];
         _insert(&mut art, &items);
 
-        let key = items.first().unwrap().clone();
-        let value = items.first().unwrap().clone();
+        let key = *items.first().unwrap();
+        let value = *items.first().unwrap();
         assert_eq!(
             art.root,
             Node::Leaf(Leaf::new(
@@ -386,14 +920,60 @@ mod tests {
         // update key A with value B
         let key = items.first().unwrap().as_bytes().to_vec();
         let new_value = "B".as_bytes().to_vec();
-        art.insert(key.clone(), new_value.clone());
+        let old_value = art.insert(key.clone(), new_value.clone());
 
+        assert_eq!(old_value, Some(key.clone()));
+        assert_eq!(art.len(), 1);
         assert_eq!(art.root, Node::Leaf(Leaf::new(key, new_value)));
     }
 
+    #[test]
+    fn test_insert_returns_old_value() {
+        let mut art = Art::new();
+        for (i, item) in ["A", "AMD", "AMDs", "Ab", "B"].iter().enumerate() {
+            assert_eq!(art.insert(item.as_bytes().to_vec(), i), None);
+        }
+        for (i, item) in ["A", "AMD", "AMDs", "Ab", "B"].iter().enumerate() {
+            assert_eq!(art.insert(item.as_bytes().to_vec(), i + 10), Some(i));
+        }
+        assert_eq!(art.len(), 5);
+        assert_eq!(art.search("AMDs".as_bytes()), Some(&12));
+    }
+
+    #[test]
+    fn test_get_mut() {
+        let mut art = Art::new();
+        assert_eq!(art.get_mut("A".as_bytes()), None);
+
+        let items = [
+            "A",
+            "AMD",
+            "AMDs",
+            "Ab",
+            "Congregationalist",
+            "Congregationalists",
+        ];
+        for item in items.iter() {
+            art.insert(item.as_bytes().to_vec(), 0);
+        }
+        for (i, item) in items.iter().enumerate() {
+            for _ in 0..i {
+                *art.get_mut(item.as_bytes()).unwrap() += 1;
+            }
+        }
+        for (i, item) in items.iter().enumerate() {
+            assert_eq!(art.search(item.as_bytes()), Some(&i));
+        }
+
+        assert_eq!(art.get_mut("AM".as_bytes()), None);
+        assert_eq!(art.get_mut("AMDx".as_bytes()), None);
+        assert_eq!(art.get_mut("Congregationalisx".as_bytes()), None);
+        assert_eq!(art.get_mut("Congregationalistss".as_bytes()), None);
+    }
+
     #[test]
     fn test_grow_16() {
-        let mut keys = (65..85).collect::<Vec<u8>>();
+        let keys = (65..85).collect::<Vec<u8>>();
         let mut art = Art::new();
         for key in keys.iter() {
             art.insert(vec![*key], vec![*key]);
@@ -405,12 +985,12 @@ mod tests {
         // TODO add test cases for these cases
         let tfn = |items: &Vec<&str>| {
             let mut art = Art::new();
-            _insert(&mut art, &items);
+            _insert(&mut art, items);
 
             for item in items.iter() {
-                let res = art.search(&item.as_bytes().to_vec());
-                if res.is_some() {
-                    let st = std::str::from_utf8(res.unwrap()).unwrap();
+                let res = art.search(item.as_bytes());
+                if let Some(res) = res {
+                    let _st = std::str::from_utf8(res).unwrap();
                 }
             }
             print_art(&art);
@@ -599,22 +1179,22 @@ mod tests {
     //        let v2 = vec![1, 2, 3, 4];
     //        let depth = 0;
     //
-    //        let prefix_len = Art::longest_common_prefix(&v1, &v2, depth);
-    //        let partial = Art::calculate_partial(&v1, depth, prefix_len);
+    //        let prefix_len = Self::longest_common_prefix(&v1, &v2, depth);
+    //        let partial = Self::calculate_partial(&v1, depth, prefix_len);
     //        assert_eq!(prefix_len, 3);
     //        assert_eq!(partial, vec![1, 2, 3]);
     //
     //        let depth = 1;
-    //        let prefix_len = Art::longest_common_prefix(&v1, &v2, depth);
-    //        let partial = Art::calculate_partial(&v1, depth, prefix_len);
+    //        let prefix_len = Self::longest_common_prefix(&v1, &v2, depth);
+    //        let partial = Self::calculate_partial(&v1, depth, prefix_len);
     //        assert_eq!(prefix_len, 2);
     //        assert_eq!(partial, vec![2, 3]);
     //
     //        let v1 = vec![1];
     //        let v2 = vec![1, 2, 3, 4];
     //        let depth = 0;
-    //        let prefix_len = Art::longest_common_prefix(&v1, &v2, depth);
-    //        let partial = Art::calculate_partial(&v1, depth, prefix_len);
+    //        let prefix_len = Self::longest_common_prefix(&v1, &v2, depth);
+    //        let partial = Self::calculate_partial(&v1, depth, prefix_len);
     //        assert_eq!(prefix_len, 1);
     //        assert_eq!(partial, vec![1]);
     //
@@ -622,8 +1202,8 @@ mod tests {
     //        let b = "Acrux".to_string().as_bytes().to_owned();
     //        let max_depth = min(a.len(), b.len());
     //        let mut depth = 0;
-    //        let prefix_len = Art::longest_common_prefix(&a, &b, depth);
-    //        let partial = Art::calculate_partial(&a, depth, prefix_len);
+    //        let prefix_len = Self::longest_common_prefix(&a, &b, depth);
+    //        let partial = Self::calculate_partial(&a, depth, prefix_len);
     //
     //        assert_eq!(prefix_len, 3);
     //        assert_eq!(partial, "Acr".as_bytes());
@@ -725,14 +1305,11 @@ mod tests {
         // check if you can find all the words
         let fil = File::open(f_name).unwrap();
         let reader = BufReader::new(fil);
-        for line in reader.lines() {
-            if line.is_ok() {
-                let line = line.unwrap();
-                let line = line.trim();
-                let res = art.search(&line.as_bytes().to_vec());
-                println!("&line = {:#?}", &line);
-                assert_eq!(res, Some(line.as_bytes()));
-            }
+        for line in reader.lines().map_while(Result::ok) {
+            let line = line.trim();
+            let res = art.search(line.as_bytes());
+            println!("&line = {:#?}", &line);
+            assert_eq!(res.map(Vec::as_slice), Some(line.as_bytes()));
         }
     }
 
@@ -743,12 +1320,556 @@ mod tests {
         _insert(&mut art, &items);
 
         for item in items {
-            let res = art.search(&item.as_bytes().to_vec());
+            let res = art.search(item.as_bytes());
             println!("res = {:#?}", res);
         }
     }
 
-    fn insert_from_file(art: &mut Art, f_name: &str) {
+    #[test]
+    fn test_remove_root_leaf() {
+        let mut art = Art::new();
+        _insert(&mut art, &vec!["A"]);
+
+        assert_eq!(art.remove("B".as_bytes()), None);
+        assert_eq!(art.remove("A".as_bytes()), Some("A".as_bytes().to_vec()));
+        assert_eq!(art.root, Node::None);
+        assert!(art.is_empty());
+        assert_eq!(art.remove("A".as_bytes()), None);
+    }
+
+    #[test]
+    fn test_remove_shrink() {
+        let mut art = Art::new();
+        for key in 0..=255u8 {
+            art.insert(vec![key], vec![key]);
+        }
+        assert!(matches!(art.root, Node::Node256(_)));
+
+        let node_type = |art: &Art<Vec<u8>, Vec<u8>>| match art.root {
+            Node::Node4(_) => "Node4",
+            Node::Node16(_) => "Node16",
+            Node::Node48(_) => "Node48",
+            Node::Node256(_) => "Node256",
+            Node::Leaf(_) => "Leaf",
+            Node::None => "None",
+        };
+
+        let mut remaining = 256;
+        for key in (0..=255u8).rev() {
+            assert_eq!(art.remove(&[key]), Some(vec![key]));
+            remaining -= 1;
+            assert_eq!(art.len(), remaining);
+
+            let expected = match remaining {
+                0 => "None",
+                1 => "Leaf",
+                2..=3 => "Node4",
+                4..=12 => "Node16",
+                13..=37 => "Node48",
+                _ => "Node256",
+            };
+            assert_eq!(node_type(&art), expected, "{} keys left", remaining);
+            for left in 0..key {
+                assert_eq!(art.search(&[left]).map(Vec::as_slice), Some(&[left][..]));
+            }
+            assert_eq!(art.search(&[key]), None);
+        }
+    }
+
+    #[test]
+    fn test_remove_merges_prefix() {
+        let mut art = Art::new();
+        let items = vec!["abcd1", "abcd2", "abcx"];
+        _insert(&mut art, &items);
+
+        assert_eq!(
+            art.remove("abcx".as_bytes()),
+            Some("abcx".as_bytes().to_vec())
+        );
+        if let Node::Node4(node) = &art.root {
+            assert_eq!(node.prefix_len(), 4);
+            assert_eq!(node.partial(), "abcd".as_bytes());
+            _verify_children(node, vec![b'1', b'2']);
+        } else {
+            panic!("Node should be of type node4 {:#?}", &art.root);
+        }
+        assert_eq!(
+            art.search("abcd1".as_bytes()).map(Vec::as_slice),
+            Some("abcd1".as_bytes())
+        );
+        assert_eq!(
+            art.search("abcd2".as_bytes()).map(Vec::as_slice),
+            Some("abcd2".as_bytes())
+        );
+
+        // long prefixes only keep MAX_PREFIX bytes in the partial
+        let mut art = Art::new();
+        let items = vec!["abcdefgh1", "abcdefgh2", "abx"];
+        _insert(&mut art, &items);
+        assert_eq!(
+            art.remove("abx".as_bytes()),
+            Some("abx".as_bytes().to_vec())
+        );
+        assert_eq!(art.root.prefix_len(), 8);
+        assert_eq!(art.root.partial(), &"abcdefgh".as_bytes()[..MAX_PREFIX]);
+        assert_eq!(
+            art.search("abcdefgh1".as_bytes()).map(Vec::as_slice),
+            Some("abcdefgh1".as_bytes())
+        );
+    }
+
+    #[test]
+    fn test_remove_term_leaf() {
+        let mut art = Art::new();
+        let items = vec!["A", "AMD", "AMDs"];
+        _insert(&mut art, &items);
+
+        assert_eq!(art.remove("AM".as_bytes()), None);
+        assert_eq!(
+            art.remove("AMD".as_bytes()),
+            Some("AMD".as_bytes().to_vec())
+        );
+        assert_eq!(art.search("AMD".as_bytes()), None);
+        assert_eq!(
+            art.search("AMDs".as_bytes()).map(Vec::as_slice),
+            Some("AMDs".as_bytes())
+        );
+        assert_eq!(art.remove("A".as_bytes()), Some("A".as_bytes().to_vec()));
+        assert_eq!(
+            art.root,
+            Node::Leaf(Leaf::new(b"AMDs".to_vec(), b"AMDs".to_vec()))
+        );
+        assert_eq!(art.len(), 1);
+    }
+
+    #[test]
+    fn test_remove_words() {
+        let f_name = "/usr/share/dict/words";
+        let mut art = Art::new();
+        insert_from_file(&mut art, f_name);
+        let words: Vec<String> = BufReader::new(File::open(f_name).unwrap())
+            .lines()
+            .map_while(Result::ok)
+            .map(|line| line.trim().to_string())
+            .collect();
+
+        let size = art.len();
+        for word in words.iter().step_by(2) {
+            assert_eq!(art.remove(word.as_bytes()), Some(word.as_bytes().to_vec()));
+        }
+        assert_eq!(art.len(), size - words.len().div_ceil(2));
+        for (index, word) in words.iter().enumerate() {
+            let expected = if index % 2 == 0 {
+                None
+            } else {
+                Some(word.as_bytes())
+            };
+            assert_eq!(art.search(word.as_bytes()).map(Vec::as_slice), expected);
+        }
+
+        for word in words.iter().skip(1).step_by(2) {
+            assert_eq!(art.remove(word.as_bytes()), Some(word.as_bytes().to_vec()));
+        }
+        assert!(art.is_empty());
+        assert_eq!(art.root, Node::None);
+    }
+
+    fn _assert_contents(art: &Art<Vec<u8>, usize>, expected: &BTreeMap<Vec<u8>, usize>) {
+        assert_eq!(art.len(), expected.len());
+        for (key, value) in expected.iter() {
+            assert_eq!(art.search(key), Some(value), "{:?}", key);
+        }
+        let items: Vec<(&[u8], &usize)> = art.iter().collect();
+        let matching: Vec<(&[u8], &usize)> =
+            expected.iter().map(|(k, v)| (k.as_slice(), v)).collect();
+        assert_eq!(items, matching);
+    }
+
+    #[test]
+    fn test_empty_key() {
+        let mut art = Art::new();
+        assert_eq!(art.search(b""), None);
+        assert_eq!(art.insert(Vec::new(), 1), None);
+        assert_eq!(art.search(b""), Some(&1));
+        assert_eq!(art.insert(b"a".to_vec(), 2), None);
+        assert_eq!(art.insert(Vec::new(), 3), Some(1));
+        assert_eq!(art.search(b""), Some(&3));
+        assert_eq!(art.search(b"a"), Some(&2));
+        assert_eq!(art.prefix_iter(b"").count(), 2);
+        assert_eq!(art.iter().next(), Some((&b""[..], &3)));
+        assert_eq!(art.remove(b""), Some(3));
+        assert_eq!(art.search(b""), None);
+        assert_eq!(art.remove(b"a"), Some(2));
+        assert_eq!(art.remove(b""), None);
+        assert!(art.is_empty());
+    }
+
+    #[test]
+    fn test_prefix_chains() {
+        // "", "a", "ab", ... chains hanging off nodes of every size, inserted shortest first,
+        // longest first and interleaved, then removed in either direction
+        for count in [2usize, 10, 40, 256].iter() {
+            let mut keys: Vec<Vec<u8>> = vec![Vec::new()];
+            for byte in (0..*count).map(|b| (b * 256 / count) as u8) {
+                for len in 1..=12 {
+                    let mut key = vec![byte];
+                    key.resize(len, b'b');
+                    keys.push(key);
+                }
+            }
+            let mut longest_first = keys.clone();
+            longest_first.sort_by_key(|key| std::cmp::Reverse(key.len()));
+            let mut interleaved = keys.clone();
+            interleaved.sort_by_key(|key| (key.len() % 3, key.len()));
+
+            for order in [keys.clone(), longest_first, interleaved].iter() {
+                let mut art = Art::new();
+                let mut expected = BTreeMap::new();
+                for (index, key) in order.iter().enumerate() {
+                    assert_eq!(art.insert(key.clone(), index), None);
+                    expected.insert(key.clone(), index);
+                }
+                _assert_contents(&art, &expected);
+                // keys that end inside a compressed path or run past a leaf are not there
+                assert_eq!(art.search(&[0, b'b', b'c']), None);
+                assert_eq!(art.search(&[0, b'c']), None);
+
+                for key in order.iter().step_by(2) {
+                    assert_eq!(art.remove(key), expected.remove(key));
+                }
+                _assert_contents(&art, &expected);
+                for key in order.iter().rev() {
+                    assert_eq!(art.remove(key), expected.remove(key));
+                }
+                assert!(art.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn test_insert_prefix_of_compressed_path() {
+        let mut art = Art::new();
+        let mut expected = BTreeMap::new();
+        let word = "Congregationalists".as_bytes();
+        // every prefix of the word, longest first so each one ends inside the path above it
+        for len in (0..=word.len()).rev() {
+            art.insert(word[..len].to_vec(), len);
+            expected.insert(word[..len].to_vec(), len);
+            _assert_contents(&art, &expected);
+        }
+        art.insert(b"Congo".to_vec(), 100);
+        expected.insert(b"Congo".to_vec(), 100);
+        _assert_contents(&art, &expected);
+
+        for len in 0..=word.len() {
+            let prefix = &word[..len];
+            let matching = expected.keys().filter(|k| k.starts_with(prefix)).count();
+            assert_eq!(art.prefix_iter(prefix).count(), matching);
+        }
+        *art.entry(Vec::new()).or_insert(0) += 1;
+        *art.entry(b"Con".to_vec()).or_insert(0) += 1;
+        assert_eq!(art.search(b""), Some(&1));
+        assert_eq!(art.search(b"Con"), Some(&4));
+    }
+
+    #[test]
+    fn test_generic_values() {
+        #[derive(Debug, PartialEq)]
+        struct Record {
+            id: u64,
+            name: String,
+        }
+
+        let mut ids: Art<Vec<u8>, u64> = Art::new();
+        let mut records = Art::new();
+        for (id, name) in ["A", "AMD", "AMDs", "B"].iter().enumerate() {
+            ids.insert(name.as_bytes().to_vec(), id as u64);
+            let name = name.to_string();
+            records.insert(
+                name.as_bytes().to_vec(),
+                Record {
+                    id: id as u64,
+                    name,
+                },
+            );
+        }
+
+        assert_eq!(ids.search("AMD".as_bytes()), Some(&1));
+        assert_eq!(ids.remove("AMDs".as_bytes()), Some(2));
+        assert_eq!(ids.search("AMDs".as_bytes()), None);
+        assert_eq!(
+            records.search("B".as_bytes()),
+            Some(&Record {
+                id: 3,
+                name: "B".to_string()
+            })
+        );
+    }
+
+    #[test]
+    fn test_try_insert_and_remove() {
+        let mut art = Art::new();
+        assert_eq!(art.try_insert(b"a".to_vec(), 1), Ok(None));
+        assert_eq!(art.try_insert(b"ab".to_vec(), 2), Ok(None));
+        assert_eq!(art.try_insert(b"a".to_vec(), 3), Ok(Some(1)));
+        assert_eq!(art.try_remove(b"abc"), Ok(None));
+        assert_eq!(art.try_remove(b"a"), Ok(Some(3)));
+        assert_eq!(art.try_remove(b"ab"), Ok(Some(2)));
+        assert!(art.is_empty());
+    }
+
+    #[test]
+    fn test_corrupted_tree() {
+        // an inner node whose compressed path is longer than `partial` but has no leaves to
+        // read the rest of it from
+        let mut node4: Node4<usize> = Node4::new();
+        node4.meta.prefix_len = 12;
+        node4.meta.partial = b"abcdefgh".to_vec();
+        let mut art = Art::new();
+        art.root = Node::Node4(node4);
+        art.size = 1;
+
+        let err = art.try_insert(b"abcdefghijkl".to_vec(), 1).unwrap_err();
+        assert!(matches!(err, ArtError::Corrupted(_)));
+        let err = art.try_insert(b"abcdXY".to_vec(), 1).unwrap_err();
+        assert!(matches!(err, ArtError::Corrupted(_)));
+        assert_eq!(art.search(b"abcdXY"), None);
+        assert!(err.to_string().starts_with("tree is corrupted: "));
+    }
+
+    #[test]
+    fn test_borrowed_lookups() {
+        let mut art: Art<String, usize> = Art::new();
+        for (idx, word) in ["", "a", "ab", "abc", "b"].iter().enumerate() {
+            art.insert(word.to_string(), idx);
+        }
+        let owned = "abc".to_string();
+        assert_eq!(art.search("abc"), Some(&3));
+        assert_eq!(art.search(&owned), Some(&3));
+        assert_eq!(art.search(owned.as_bytes()), Some(&3));
+        assert_eq!(art.search(b"abc"), Some(&3));
+        assert_eq!(art.search(&Cow::Borrowed(&b"ab"[..])), Some(&2));
+        assert_eq!(art.search(&Box::<[u8]>::from(&b"a"[..])), Some(&1));
+        assert_eq!(art.search(""), Some(&0));
+        assert!(art.contains_key("b"));
+        assert!(!art.contains_key("abcd"));
+        *art.get_mut("ab").unwrap() += 10;
+        assert_eq!(art.remove("ab"), Some(12));
+        assert!(!art.contains_key(b"ab"));
+
+        let mut boxed: Art<Box<[u8]>, usize> = Art::new();
+        boxed.insert(b"boxed".to_vec().into_boxed_slice(), 1);
+        let mut cows: Art<Cow<'_, str>, usize> = Art::new();
+        cows.insert(Cow::Borrowed("borrowed"), 2);
+        cows.insert(Cow::Owned("owned".to_string()), 3);
+        assert_eq!(boxed.search("boxed"), Some(&1));
+        assert_eq!(cows.search("borrowed"), Some(&2));
+        let keys: Vec<Cow<'_, str>> = cows.iter().decoded().map(|(k, _)| k).collect();
+        assert_eq!(keys, vec!["borrowed", "owned"]);
+    }
+
+    #[test]
+    fn test_collection_traits() {
+        let words = ["", "a", "ab", "abc", "b", "ba"];
+        let mut art: Art<String, usize> = words
+            .iter()
+            .enumerate()
+            .map(|(idx, word)| (word.to_string(), idx))
+            .collect();
+        art.extend(vec![("c".to_string(), 6), ("a".to_string(), 7)]);
+        assert_eq!(art.len(), 7);
+        assert_eq!(art["a"], 7);
+        assert_eq!(art[&b"ba"[..]], 5);
+        assert_eq!(art[&"c".to_string()], 6);
+
+        let mut visited = Vec::new();
+        for (key, value) in &art {
+            visited.push((key.to_vec(), *value));
+        }
+        assert_eq!(visited.len(), 7);
+        for (key, value) in &mut art {
+            *value *= key.len();
+        }
+        let owned: Vec<(String, usize)> = art.into_iter().collect();
+        let expected: Vec<(String, usize)> = vec![
+            ("".to_string(), 0),
+            ("a".to_string(), 7),
+            ("ab".to_string(), 4),
+            ("abc".to_string(), 9),
+            ("b".to_string(), 4),
+            ("ba".to_string(), 10),
+            ("c".to_string(), 6),
+        ];
+        assert_eq!(owned, expected);
+
+        let default: Art<u32, ()> = Art::default();
+        assert!(default.into_iter().next().is_none());
+        let reader = BufReader::new(File::open("/usr/share/dict/words").unwrap());
+        let expected: BTreeMap<Vec<u8>, usize> = reader
+            .lines()
+            .map_while(Result::ok)
+            .enumerate()
+            .map(|(idx, line)| (line.trim().as_bytes().to_vec(), idx))
+            .collect();
+        let words: Art<Vec<u8>, usize> = Art::from_iter(expected.clone());
+        assert!(words.into_iter().eq(expected.into_iter()));
+    }
+
+    #[test]
+    #[should_panic(expected = "key not found")]
+    fn test_index_missing_key() {
+        let art: Art<String, usize> = vec![("a".to_string(), 1)].into_iter().collect();
+        let _ = art["b"];
+    }
+
+    #[test]
+    fn test_longest_prefix_match() {
+        let routes = [
+            "/",
+            "/api",
+            "/api/",
+            "/api/users",
+            "/static/css/",
+            "/static/images/",
+        ];
+        let art: Art<String, usize> = routes
+            .iter()
+            .enumerate()
+            .map(|(idx, route)| (route.to_string(), idx))
+            .collect();
+        let lookup = |path: &str| art.longest_prefix_match(path).map(|(_, idx)| routes[*idx]);
+        assert_eq!(lookup("/api/users/17"), Some("/api/users"));
+        assert_eq!(lookup("/api/user"), Some("/api/"));
+        assert_eq!(lookup("/api"), Some("/api"));
+        assert_eq!(lookup("/static/css"), Some("/"));
+        assert_eq!(lookup("/static/images/x.png"), Some("/static/images/"));
+        assert_eq!(lookup("index.html"), None);
+        assert_eq!(lookup(""), None);
+        let prefixes: Vec<&[u8]> = art.prefixes_of("/api/users/").map(|(k, _)| k).collect();
+        assert_eq!(prefixes, vec![&b"/"[..], b"/api", b"/api/", b"/api/users"]);
+
+        // compressed paths longer than the partial that stop matching past it
+        let mut art: Art<Vec<u8>, usize> = Art::new();
+        for key in ["aaaaaaaaaaaa", "aaaaaaaaaaaaXb", "aaaaaaaaaaaaYb"].iter() {
+            art.insert(key.as_bytes().to_vec(), key.len());
+        }
+        assert_eq!(
+            art.longest_prefix_match(b"aaaaaaaaaaaaXbc"),
+            Some((&b"aaaaaaaaaaaaXb"[..], &14))
+        );
+        assert_eq!(
+            art.longest_prefix_match(b"aaaaaaaaaaaaXc"),
+            Some((&b"aaaaaaaaaaaa"[..], &12))
+        );
+        assert_eq!(art.longest_prefix_match(b"aaaaaaaaaaabXb"), None);
+        assert_eq!(art.longest_prefix_match(b"aaaaaaaaaaa"), None);
+        art.remove(b"aaaaaaaaaaaa");
+        assert_eq!(art.longest_prefix_match(b"aaaaaaaaaaaaXc"), None);
+        assert_eq!(art.prefixes_of(b"aaaaaaaaaaaaYbb").count(), 1);
+    }
+
+    #[test]
+    fn test_prefixes_of_words() {
+        let reader = BufReader::new(File::open("/usr/share/dict/words").unwrap());
+        let mut art = Art::new();
+        let mut expected = BTreeMap::new();
+        for (idx, line) in reader.lines().map_while(Result::ok).enumerate() {
+            let key = line.trim().as_bytes().to_vec();
+            if idx % 2 == 0 {
+                art.insert(key.clone(), idx);
+                expected.insert(key, idx);
+            }
+        }
+
+        let words: Vec<Vec<u8>> = expected.keys().step_by(7).cloned().collect();
+        for word in words {
+            for input in [word.clone(), [&word[..], b"ing"].concat()].iter() {
+                let prefixes: Vec<(&[u8], &usize)> = (0..=input.len())
+                    .filter_map(|len| expected.get_key_value(&input[..len]))
+                    .map(|(key, value)| (key.as_slice(), value))
+                    .collect();
+                assert!(art.prefixes_of(input).eq(prefixes.iter().cloned()));
+                assert_eq!(art.longest_prefix_match(input), prefixes.last().cloned());
+            }
+        }
+    }
+
+    fn _hash<T: Hash>(value: &T) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    #[test]
+    fn test_content_comparisons() {
+        // a node16 shrinks only once it is down to 3 children, so the same 4 entries are laid
+        // out in a node4 in one tree and a node16 in the other
+        let mut grown: Art<String, usize> = Art::new();
+        for (idx, key) in ["ka", "kb", "kc", "kd", "ke"].iter().enumerate() {
+            grown.insert(key.to_string(), idx);
+        }
+        grown.remove("ke");
+        let built: Art<String, usize> = ["kd", "kc", "kb", "ka"]
+            .iter()
+            .enumerate()
+            .map(|(idx, key)| (key.to_string(), 3 - idx))
+            .collect();
+        assert_ne!(
+            format!("{:?}", grown.structure()),
+            format!("{:?}", built.structure())
+        );
+        assert_eq!(grown, built);
+        assert_eq!(grown.cmp(&built), Ordering::Equal);
+        assert_eq!(_hash(&grown), _hash(&built));
+
+        // ordered like the equivalent BTreeMaps
+        let maps: Vec<Vec<(&str, usize)>> = vec![
+            vec![],
+            vec![("", 0)],
+            vec![("a", 1)],
+            vec![("a", 2)],
+            vec![("a", 1), ("b", 1)],
+            vec![("ab", 0)],
+            vec![("b", 0)],
+        ];
+        for one in maps.iter() {
+            for two in maps.iter() {
+                let art = |entries: &Vec<(&str, usize)>| -> Art<String, usize> {
+                    entries.iter().map(|(k, v)| (k.to_string(), *v)).collect()
+                };
+                let btree = |entries: &Vec<(&str, usize)>| -> BTreeMap<String, usize> {
+                    entries.iter().map(|(k, v)| (k.to_string(), *v)).collect()
+                };
+                assert_eq!(art(one).cmp(&art(two)), btree(one).cmp(&btree(two)));
+                assert_eq!(art(one) == art(two), one == two);
+                assert_eq!(art(one).partial_cmp(&art(two)), one.partial_cmp(two));
+            }
+        }
+        let floats: Art<u8, f64> = vec![(1, f64::NAN)].into_iter().collect();
+        assert!(floats != floats);
+        assert_eq!(floats.partial_cmp(&floats), None);
+    }
+
+    #[test]
+    fn test_debug_format() {
+        let mut art: Art<String, usize> = Art::new();
+        assert_eq!(format!("{:?}", art), "{}");
+        art.insert("b".to_string(), 2);
+        art.insert("a".to_string(), 1);
+        assert_eq!(format!("{:?}", art), r#"{"a": 1, "b": 2}"#);
+
+        // keys that don't decode are printed as their bytes, the public methods only ever
+        // store encodings of the key type so the bytes go into the nodes directly
+        Node::insert_at(&mut art.root, 0, vec![b'c', 0xff], 3).unwrap();
+        art.size += 1;
+        assert_eq!(format!("{:?}", art), r#"{"a": 1, "b": 2, [99, 255]: 3}"#);
+
+        let bytes: Art<Vec<u8>, ()> = vec![(b"xy".to_vec(), ())].into_iter().collect();
+        assert_eq!(format!("{:?}", bytes), "{[120, 121]: ()}");
+        assert!(format!("{:?}", bytes.structure()).starts_with("Leaf(Leaf { key: [120, 121]"));
+    }
+
+    fn insert_from_file(art: &mut Art<Vec<u8>, Vec<u8>>, f_name: &str) {
         let fil = File::open(f_name).unwrap();
         let mut reader = BufReader::new(fil);
         loop {
@@ -758,8 +1879,8 @@ mod tests {
                 break;
             }
             art.insert(
-                buffer.trim().clone().as_bytes().to_vec(),
-                buffer.trim().clone().as_bytes().to_vec(),
+                buffer.trim().as_bytes().to_vec(),
+                buffer.trim().as_bytes().to_vec(),
             );
         }
     }
@@ -771,16 +1892,16 @@ mod tests {
     //        let c = "davidbrainard".to_string();
     //        let d = "davibrainard".to_string();
     //
-    //        let res = Art::equals(a.as_bytes(), a.as_bytes());
+    //        let res = Self::equals(a.as_bytes(), a.as_bytes());
     //        assert_eq!(true, res);
     //
-    //        let res = Art::equals(a.as_bytes(), b.as_bytes());
+    //        let res = Self::equals(a.as_bytes(), b.as_bytes());
     //        assert_eq!(false, res);
     //
-    //        let res = Art::equals(a.as_bytes(), c.as_bytes());
+    //        let res = Self::equals(a.as_bytes(), c.as_bytes());
     //        assert_eq!(false, res);
     //
-    //        let res = Art::equals(a.as_bytes(), d.as_bytes());
+    //        let res = Self::equals(a.as_bytes(), d.as_bytes());
     //        assert_eq!(false, res);
     //    }
     //
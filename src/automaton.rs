@@ -0,0 +1,613 @@
+use crate::{
+    ArtKey, Automaton, LiteralPrefix, NfaState, Regex, RegexError, RegexParser, Subsequence,
+};
+use std::collections::HashMap;
+
+// the most states a regex compiles to before it is turned down, subset construction can blow
+// up exponentially on some patterns
+const MAX_DFA_STATES: usize = 10_000;
+
+const DEAD: u32 = 0;
+
+impl<A: Automaton + ?Sized> Automaton for &A {
+    type State = A::State;
+
+    fn start(&self) -> Self::State {
+        (**self).start()
+    }
+
+    fn accept(&self, state: &Self::State, byte: u8) -> Self::State {
+        (**self).accept(state, byte)
+    }
+
+    fn is_match(&self, state: &Self::State) -> bool {
+        (**self).is_match(state)
+    }
+
+    fn can_match(&self, state: &Self::State) -> bool {
+        (**self).can_match(state)
+    }
+
+    fn next_bytes(&self, state: &Self::State) -> Option<Vec<u8>> {
+        (**self).next_bytes(state)
+    }
+}
+
+impl LiteralPrefix {
+    /// Returns an automaton matching the keys whose encoding starts with the encoding of
+    /// `prefix`.
+    pub fn new<Q: ArtKey + ?Sized>(prefix: &Q) -> Self {
+        LiteralPrefix {
+            prefix: prefix.encode().into_owned(),
+        }
+    }
+}
+
+// the number of bytes of the prefix matched so far, None once a byte didn't match
+impl Automaton for LiteralPrefix {
+    type State = Option<usize>;
+
+    fn start(&self) -> Self::State {
+        Some(0)
+    }
+
+    fn accept(&self, state: &Self::State, byte: u8) -> Self::State {
+        match *state {
+            Some(matched) if matched == self.prefix.len() => Some(matched),
+            Some(matched) if self.prefix[matched] == byte => Some(matched + 1),
+            _ => None,
+        }
+    }
+
+    fn is_match(&self, state: &Self::State) -> bool {
+        *state == Some(self.prefix.len())
+    }
+
+    fn can_match(&self, state: &Self::State) -> bool {
+        state.is_some()
+    }
+
+    // only the next byte of the prefix, until all of it is matched
+    fn next_bytes(&self, state: &Self::State) -> Option<Vec<u8>> {
+        match *state {
+            Some(matched) if matched < self.prefix.len() => Some(vec![self.prefix[matched]]),
+            Some(_) => None,
+            None => Some(Vec::new()),
+        }
+    }
+}
+
+impl Subsequence {
+    /// Returns an automaton matching the keys that hold the bytes of `needle` in order.
+    pub fn new<N: AsRef<[u8]> + ?Sized>(needle: &N) -> Self {
+        Subsequence {
+            needle: needle.as_ref().to_vec(),
+        }
+    }
+}
+
+// the number of bytes of the needle found so far, taking every byte as early as it comes
+impl Automaton for Subsequence {
+    type State = usize;
+
+    fn start(&self) -> Self::State {
+        0
+    }
+
+    fn accept(&self, state: &Self::State, byte: u8) -> Self::State {
+        match self.needle.get(*state) {
+            Some(expected) if *expected == byte => state + 1,
+            _ => *state,
+        }
+    }
+
+    fn is_match(&self, state: &Self::State) -> bool {
+        *state == self.needle.len()
+    }
+}
+
+impl Regex {
+    /// Compiles `pattern`, returning an error with the offset it was found at if the pattern
+    /// is malformed or compiles to too many states.
+    pub fn new(pattern: &str) -> Result<Self, RegexError> {
+        let mut parser = RegexParser {
+            pattern: pattern.as_bytes(),
+            position: 0,
+            states: Vec::new(),
+        };
+        let (start, accept) = parser.parse_alternation()?;
+        if parser.position < parser.pattern.len() {
+            return Err(parser.error("unmatched closing parenthesis"));
+        }
+        Self::determinize(&parser.states, start, accept)
+    }
+
+    /// Returns `true` if the regex matches the whole of `bytes`.
+    pub fn matches(&self, bytes: &[u8]) -> bool {
+        let state = bytes
+            .iter()
+            .fold(self.start(), |state, byte| self.accept(&state, *byte));
+        self.is_match(&state)
+    }
+
+    // the subset construction, every state of the DFA standing for the set of NFA states the
+    // bytes read so far can lead to
+    fn determinize(states: &[NfaState], start: usize, accept: usize) -> Result<Self, RegexError> {
+        let mut sets = vec![Vec::new(), Self::closure(states, vec![start])];
+        let mut ids: HashMap<Vec<usize>, u32> = HashMap::new();
+        ids.insert(sets[0].clone(), DEAD);
+        ids.insert(sets[1].clone(), 1);
+        let mut table = vec![DEAD; 256];
+
+        let mut current = 1;
+        while current < sets.len() {
+            for byte in 0..=255u8 {
+                let mut targets = Vec::new();
+                for state in sets[current].iter() {
+                    for (bytes, target) in states[*state].bytes.iter() {
+                        if bytes[byte as usize] {
+                            targets.push(*target);
+                        }
+                    }
+                }
+                let targets = Self::closure(states, targets);
+                let id = match ids.get(&targets) {
+                    Some(id) => *id,
+                    None if sets.len() == MAX_DFA_STATES => {
+                        return Err(RegexError {
+                            position: 0,
+                            reason: "the pattern needs too many states",
+                        })
+                    }
+                    None => {
+                        let id = sets.len() as u32;
+                        ids.insert(targets.clone(), id);
+                        sets.push(targets);
+                        id
+                    }
+                };
+                table.push(id);
+            }
+            current += 1;
+        }
+
+        let accepting: Vec<bool> = sets.iter().map(|set| set.contains(&accept)).collect();
+        let mut live = accepting.clone();
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for state in 0..sets.len() {
+                if !live[state]
+                    && table[state * 256..(state + 1) * 256]
+                        .iter()
+                        .any(|next| live[*next as usize])
+                {
+                    live[state] = true;
+                    changed = true;
+                }
+            }
+        }
+        Ok(Regex {
+            table,
+            accepting,
+            live,
+        })
+    }
+
+    // the states reachable without reading a byte, sorted so that equal sets compare equal
+    fn closure(states: &[NfaState], mut set: Vec<usize>) -> Vec<usize> {
+        let mut idx = 0;
+        while idx < set.len() {
+            for next in states[set[idx]].epsilon.iter() {
+                if !set.contains(next) {
+                    set.push(*next);
+                }
+            }
+            idx += 1;
+        }
+        set.sort_unstable();
+        set
+    }
+}
+
+impl Automaton for Regex {
+    type State = u32;
+
+    fn start(&self) -> Self::State {
+        1
+    }
+
+    fn accept(&self, state: &Self::State, byte: u8) -> Self::State {
+        self.table[*state as usize * 256 + byte as usize]
+    }
+
+    fn is_match(&self, state: &Self::State) -> bool {
+        self.accepting[*state as usize]
+    }
+
+    fn can_match(&self, state: &Self::State) -> bool {
+        self.live[*state as usize]
+    }
+}
+
+impl<'p> RegexParser<'p> {
+    fn error(&self, reason: &'static str) -> RegexError {
+        RegexError {
+            position: self.position,
+            reason,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.pattern.get(self.position).copied()
+    }
+
+    fn state(&mut self) -> usize {
+        self.states.push(NfaState {
+            epsilon: Vec::new(),
+            bytes: Vec::new(),
+        });
+        self.states.len() - 1
+    }
+
+    fn link(&mut self, from: usize, to: usize) {
+        self.states[from].epsilon.push(to);
+    }
+
+    // branch ('|' branch)*
+    fn parse_alternation(&mut self) -> Result<(usize, usize), RegexError> {
+        let mut branches = vec![self.parse_concatenation()?];
+        while self.peek() == Some(b'|') {
+            self.position += 1;
+            branches.push(self.parse_concatenation()?);
+        }
+        if branches.len() == 1 {
+            return Ok(branches[0]);
+        }
+        let (start, end) = (self.state(), self.state());
+        for (branch_start, branch_end) in branches {
+            self.link(start, branch_start);
+            self.link(branch_end, end);
+        }
+        Ok((start, end))
+    }
+
+    // repetition*, which matches the empty string when there are none
+    fn parse_concatenation(&mut self) -> Result<(usize, usize), RegexError> {
+        let start = self.state();
+        let mut end = start;
+        while let Some(byte) = self.peek() {
+            if byte == b'|' || byte == b')' {
+                break;
+            }
+            let (next_start, next_end) = self.parse_repetition()?;
+            self.link(end, next_start);
+            end = next_end;
+        }
+        Ok((start, end))
+    }
+
+    // atom ('*' | '+' | '?')*
+    fn parse_repetition(&mut self) -> Result<(usize, usize), RegexError> {
+        let (mut start, mut end) = self.parse_atom()?;
+        while let Some(operator) = self.peek() {
+            if !matches!(operator, b'*' | b'+' | b'?') {
+                break;
+            }
+            self.position += 1;
+            let (outer_start, outer_end) = (self.state(), self.state());
+            self.link(outer_start, start);
+            self.link(end, outer_end);
+            if operator != b'+' {
+                self.link(outer_start, outer_end);
+            }
+            if operator != b'?' {
+                self.link(end, start);
+            }
+            start = outer_start;
+            end = outer_end;
+        }
+        Ok((start, end))
+    }
+
+    fn parse_atom(&mut self) -> Result<(usize, usize), RegexError> {
+        let byte = match self.peek() {
+            Some(byte) => byte,
+            None => return Err(self.error("expected an expression")),
+        };
+        let bytes = match byte {
+            b'(' => {
+                let open = self.position;
+                self.position += 1;
+                let group = self.parse_alternation()?;
+                if self.peek() != Some(b')') {
+                    self.position = open;
+                    return Err(self.error("unclosed group"));
+                }
+                self.position += 1;
+                return Ok(group);
+            }
+            b'*' | b'+' | b'?' => return Err(self.error("nothing to repeat")),
+            b'.' => {
+                self.position += 1;
+                [true; 256]
+            }
+            b'[' => self.parse_class()?,
+            b'\\' => {
+                self.position += 1;
+                self.parse_escape()?
+            }
+            byte => {
+                self.position += 1;
+                let mut bytes = [false; 256];
+                bytes[byte as usize] = true;
+                bytes
+            }
+        };
+        let (start, end) = (self.state(), self.state());
+        self.states[start].bytes.push((bytes, end));
+        Ok((start, end))
+    }
+
+    // the byte or class of bytes for the escape after a backslash
+    fn parse_escape(&mut self) -> Result<[bool; 256], RegexError> {
+        let byte = match self.peek() {
+            Some(byte) => byte,
+            None => return Err(self.error("trailing backslash")),
+        };
+        self.position += 1;
+        let mut bytes = [false; 256];
+        let class: &dyn Fn(u8) -> bool = match byte.to_ascii_lowercase() {
+            b'd' => &|byte: u8| byte.is_ascii_digit(),
+            b'w' => &|byte: u8| byte.is_ascii_alphanumeric() || byte == b'_',
+            b's' => &|byte: u8| b" \t\n\r\x0b\x0c".contains(&byte),
+            _ => {
+                let literal = match byte {
+                    b'n' => b'\n',
+                    b't' => b'\t',
+                    b'r' => b'\r',
+                    byte => byte,
+                };
+                bytes[literal as usize] = true;
+                return Ok(bytes);
+            }
+        };
+        for (member, set) in bytes.iter_mut().enumerate() {
+            *set = class(member as u8) != byte.is_ascii_uppercase();
+        }
+        Ok(bytes)
+    }
+
+    // a class up to its closing bracket, where a ']' right after the '[' or '[^' is a member
+    fn parse_class(&mut self) -> Result<[bool; 256], RegexError> {
+        let open = self.position;
+        self.position += 1;
+        let negated = self.peek() == Some(b'^');
+        if negated {
+            self.position += 1;
+        }
+        let first = self.position;
+        let mut bytes = [false; 256];
+        loop {
+            let low = match self.peek() {
+                None => {
+                    self.position = open;
+                    return Err(self.error("unclosed class"));
+                }
+                Some(b']') if self.position > first => break,
+                Some(b'\\') => {
+                    self.position += 1;
+                    let escaped = self.parse_escape()?;
+                    if escaped.iter().filter(|member| **member).count() > 1 {
+                        for (member, set) in bytes.iter_mut().zip(escaped.iter()) {
+                            *member |= *set;
+                        }
+                        continue;
+                    }
+                    escaped.iter().position(|member| *member).unwrap_or(0) as u8
+                }
+                Some(byte) => {
+                    self.position += 1;
+                    byte
+                }
+            };
+            let mut high = low;
+            let ends_range = self.pattern.get(self.position + 1).copied();
+            if self.peek() == Some(b'-') && ends_range.is_some() && ends_range != Some(b']') {
+                self.position += 1;
+                high = match self.peek() {
+                    Some(b'\\') => {
+                        let escape = self.position;
+                        self.position += 1;
+                        let escaped = self.parse_escape()?;
+                        if escaped.iter().filter(|member| **member).count() > 1 {
+                            self.position = escape;
+                            return Err(self.error("class escape can't end a range"));
+                        }
+                        escaped.iter().position(|member| *member).unwrap_or(0) as u8
+                    }
+                    _ => {
+                        self.position += 1;
+                        self.pattern[self.position - 1]
+                    }
+                };
+                if high < low {
+                    return Err(self.error("range out of order"));
+                }
+            }
+            for member in low..=high {
+                bytes[member as usize] = true;
+            }
+        }
+        self.position += 1;
+        if negated {
+            for member in bytes.iter_mut() {
+                *member = !*member;
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Art;
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+
+    fn _words() -> Art<Vec<u8>, usize> {
+        let reader = BufReader::new(File::open("/usr/share/dict/words").unwrap());
+        reader
+            .lines()
+            .map_while(Result::ok)
+            .enumerate()
+            .map(|(idx, line)| (line.trim().as_bytes().to_vec(), idx))
+            .collect()
+    }
+
+    #[test]
+    fn test_regex_syntax() {
+        let cases: Vec<(&str, Vec<&str>, Vec<&str>)> = vec![
+            ("", vec![""], vec!["a"]),
+            ("abc", vec!["abc"], vec!["ab", "abcd", ""]),
+            ("a.c", vec!["abc", "a.c", "a\nc"], vec!["ac", "abbc"]),
+            ("ab*c", vec!["ac", "abc", "abbbc"], vec!["abd"]),
+            ("ab+c", vec!["abc", "abbc"], vec!["ac"]),
+            ("ab?c", vec!["ac", "abc"], vec!["abbc"]),
+            (
+                "(ab|cd)+e?",
+                vec!["ab", "cdab", "abcde"],
+                vec!["", "abe e", "ace"],
+            ),
+            ("a|b|", vec!["a", "b", ""], vec!["ab"]),
+            ("[a-c_]+", vec!["a_b", "cab"], vec!["", "d"]),
+            ("[^0-9]", vec!["a", "-"], vec!["5", "ab"]),
+            ("[]a]*", vec!["]a]"], vec!["["]),
+            ("[a-]", vec!["a", "-"], vec!["b"]),
+            (r"\d+\.\d*", vec!["3.14", "10."], vec![".5", "1"]),
+            (r"\w\s\W\S\D", vec!["a !xy"], vec!["a ! x", "a !x1"]),
+            (r"[\d\-x]+", vec!["1-x", "x"], vec!["a"]),
+            (r"[\]\\]", vec!["]", "\\"], vec!["a"]),
+            (r"\(\)\*", vec!["()*"], vec!["("]),
+            ("(a*)*b", vec!["b", "aaab"], vec!["aa"]),
+        ];
+        for (pattern, matching, failing) in cases.iter() {
+            let regex = Regex::new(pattern).unwrap();
+            for key in matching.iter() {
+                assert!(regex.matches(key.as_bytes()), "{} {:?}", pattern, key);
+            }
+            for key in failing.iter() {
+                assert!(!regex.matches(key.as_bytes()), "{} {:?}", pattern, key);
+            }
+        }
+
+        let errors = [
+            ("(ab", 0, "unclosed group"),
+            ("ab)", 2, "unmatched closing parenthesis"),
+            ("*a", 0, "nothing to repeat"),
+            ("a|*", 2, "nothing to repeat"),
+            ("[ab", 0, "unclosed class"),
+            ("[z-a]", 4, "range out of order"),
+            (r"[!-\d]", 3, "class escape can't end a range"),
+            ("ab\\", 3, "trailing backslash"),
+        ];
+        for (pattern, position, reason) in errors.iter() {
+            let err = Regex::new(pattern).unwrap_err();
+            assert_eq!(
+                err,
+                RegexError {
+                    position: *position,
+                    reason
+                }
+            );
+        }
+        assert_eq!(
+            Regex::new("(").unwrap_err().to_string(),
+            "invalid regex at 0: unclosed group"
+        );
+        assert!(Regex::new("(a|b)*a(a|b)(a|b)(a|b)(a|b)(a|b)(a|b)(a|b)(a|b)").is_ok());
+    }
+
+    #[test]
+    fn test_dead_states_prune() {
+        let regex = Regex::new("ab[0-9]").unwrap();
+        let state = regex.accept(&regex.start(), b'x');
+        assert!(!regex.can_match(&state));
+        let state = regex.accept(&regex.start(), b'a');
+        assert!(regex.can_match(&state) && !regex.is_match(&state));
+
+        // a subtree left behind at the first byte isn't walked any further
+        struct Counting<'r> {
+            regex: &'r Regex,
+            steps: std::cell::Cell<usize>,
+        }
+        impl<'r> Automaton for Counting<'r> {
+            type State = u32;
+            fn start(&self) -> u32 {
+                self.regex.start()
+            }
+            fn accept(&self, state: &u32, byte: u8) -> u32 {
+                self.steps.set(self.steps.get() + 1);
+                self.regex.accept(state, byte)
+            }
+            fn is_match(&self, state: &u32) -> bool {
+                self.regex.is_match(state)
+            }
+            fn can_match(&self, state: &u32) -> bool {
+                self.regex.can_match(state)
+            }
+        }
+        let mut art = Art::new();
+        for idx in 0..1000u32 {
+            art.insert(format!("zz{}", idx), idx);
+        }
+        art.insert("ab1".to_string(), 1000);
+        let counting = Counting {
+            regex: &regex,
+            steps: std::cell::Cell::new(0),
+        };
+        let found: Vec<&[u8]> = art.search_automaton(&counting).map(|(k, _)| k).collect();
+        assert_eq!(found, vec![&b"ab1"[..]]);
+        assert!(counting.steps.get() < 10);
+    }
+
+    #[test]
+    fn test_built_in_automata() {
+        let art = _words();
+        for prefix in ["", "a", "ab", "inter", "zzzzzz"].iter() {
+            let found = art.search_automaton(LiteralPrefix::new(*prefix));
+            assert!(found.eq(art.prefix_iter(*prefix)), "{}", prefix);
+        }
+
+        for needle in ["", "q", "aeiou", "xyz", "zq"].iter() {
+            let found = art.search_automaton(Subsequence::new(*needle));
+            let expected = art.iter().filter(|(key, _)| {
+                let mut rest = key.iter();
+                needle.bytes().all(|byte| rest.any(|other| *other == byte))
+            });
+            assert!(found.eq(expected), "{}", needle);
+        }
+
+        let patterns = [
+            "a.*ing",
+            "(re|un)[a-z]+(ed|s)",
+            "[^aeiou]*",
+            "[a-c]..?",
+            "q[^u].*",
+            ".*(ab|ba).*(ab|ba).*",
+            "x+|y+|z+",
+        ];
+        for pattern in patterns.iter() {
+            let regex = Regex::new(pattern).unwrap();
+            let found = art.search_automaton(&regex);
+            let expected = art.iter().filter(|(key, _)| regex.matches(key));
+            assert!(found.eq(expected), "{}", pattern);
+        }
+        let regex = Regex::new("a.*ing").unwrap();
+        let expected = art
+            .iter()
+            .filter(|(key, _)| key.starts_with(b"a") && key.ends_with(b"ing") && key.len() >= 4);
+        assert!(art.search_automaton(&regex).eq(expected));
+    }
+}
@@ -0,0 +1,327 @@
+use crate::key::{decode_escaped, encode_escaped};
+use crate::{ArtKey, KeyBuilder, KeyLookup, KeyReader, Segment};
+use std::borrow::Cow;
+
+// the type codes segments start with, a key sorts segments of different types by these
+const BYTES: u8 = 0x01;
+const STR: u8 = 0x02;
+const I64: u8 = 0x14;
+const U64: u8 = 0x15;
+
+impl KeyBuilder {
+    pub fn new() -> Self {
+        KeyBuilder { buf: Vec::new() }
+    }
+
+    /// Appends a byte string segment.
+    pub fn push_bytes(mut self, segment: &[u8]) -> Self {
+        self.buf.push(BYTES);
+        encode_escaped(segment, &mut self.buf);
+        self
+    }
+
+    /// Appends a string segment.
+    pub fn push_str(mut self, segment: &str) -> Self {
+        self.buf.push(STR);
+        encode_escaped(segment.as_bytes(), &mut self.buf);
+        self
+    }
+
+    /// Appends a signed integer segment.
+    pub fn push_i64(mut self, segment: i64) -> Self {
+        self.buf.push(I64);
+        segment.encode_into(&mut self.buf);
+        self
+    }
+
+    /// Appends an unsigned integer segment.
+    pub fn push_u64(mut self, segment: u64) -> Self {
+        self.buf.push(U64);
+        segment.encode_into(&mut self.buf);
+        self
+    }
+
+    /// Appends a segment of any type.
+    pub fn push(self, segment: &Segment) -> Self {
+        match segment {
+            Segment::Bytes(bytes) => self.push_bytes(bytes),
+            Segment::Str(string) => self.push_str(string),
+            Segment::I64(int) => self.push_i64(*int),
+            Segment::U64(int) => self.push_u64(*int),
+        }
+    }
+
+    /// Returns the key built so far, which can be used as the prefix of a scan over the keys
+    /// that start with the same segments.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+// a built key is as long as its segments, so in a tuple it escapes and terminates its bytes the
+// way a byte string does
+impl ArtKey for KeyBuilder {
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.buf);
+    }
+
+    fn encode(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.buf)
+    }
+
+    fn into_encoded(self) -> Vec<u8> {
+        self.buf
+    }
+
+    fn encode_nested(&self, buf: &mut Vec<u8>) {
+        encode_escaped(&self.buf, buf);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        Self::from_encoded(bytes.to_vec())
+    }
+
+    fn from_encoded(bytes: Vec<u8>) -> Option<Self> {
+        let mut reader = KeyReader::new(&bytes);
+        while reader.next().is_some() {}
+        if reader.is_empty() {
+            Some(KeyBuilder { buf: bytes })
+        } else {
+            None
+        }
+    }
+
+    fn decode_nested(bytes: &mut &[u8]) -> Option<Self> {
+        Self::from_encoded(decode_escaped(bytes)?)
+    }
+}
+
+// a built key encodes to the bytes it was built into, so it stands for byte string keys holding
+// those bytes and the other way around
+impl KeyLookup<Vec<u8>> for KeyBuilder {}
+impl KeyLookup<Box<[u8]>> for KeyBuilder {}
+impl KeyLookup<Cow<'_, [u8]>> for KeyBuilder {}
+impl KeyLookup<KeyBuilder> for [u8] {}
+impl KeyLookup<KeyBuilder> for Vec<u8> {}
+
+impl<'a> KeyReader<'a> {
+    pub fn new(key: &'a [u8]) -> Self {
+        KeyReader { bytes: key }
+    }
+
+    /// Returns `true` once every segment has been read.
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Returns the bytes of the segments that haven't been read yet.
+    pub fn remaining(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    /// Reads the next segment if it is a byte string.
+    pub fn read_bytes(&mut self) -> Option<Vec<u8>> {
+        self.read(BYTES, decode_escaped)
+    }
+
+    /// Reads the next segment if it is a string.
+    pub fn read_str(&mut self) -> Option<String> {
+        self.read(STR, |bytes| String::from_utf8(decode_escaped(bytes)?).ok())
+    }
+
+    /// Reads the next segment if it is a signed integer.
+    pub fn read_i64(&mut self) -> Option<i64> {
+        self.read(I64, i64::decode_nested)
+    }
+
+    /// Reads the next segment if it is an unsigned integer.
+    pub fn read_u64(&mut self) -> Option<u64> {
+        self.read(U64, u64::decode_nested)
+    }
+
+    // reads the segment after the type code, leaving the reader where it was unless both the
+    // code and the segment are the expected ones
+    fn read<T, F>(&mut self, code: u8, read: F) -> Option<T>
+    where
+        F: FnOnce(&mut &'a [u8]) -> Option<T>,
+    {
+        let (first, mut rest) = self.bytes.split_first()?;
+        if *first != code {
+            return None;
+        }
+        let segment = read(&mut rest)?;
+        self.bytes = rest;
+        Some(segment)
+    }
+}
+
+/// Reads the remaining segments, stopping at the end of the key or at the first malformed
+/// segment, which `is_empty` tells apart.
+impl<'a> Iterator for KeyReader<'a> {
+    type Item = Segment;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match *self.bytes.first()? {
+            BYTES => self.read_bytes().map(Segment::Bytes),
+            STR => self.read_str().map(Segment::Str),
+            I64 => self.read_i64().map(Segment::I64),
+            U64 => self.read_u64().map(Segment::U64),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Art;
+
+    fn _key(tenant: &[u8], table: &str, row: i64) -> Vec<u8> {
+        KeyBuilder::new()
+            .push_bytes(tenant)
+            .push_str(table)
+            .push_i64(row)
+            .finish()
+    }
+
+    #[test]
+    fn test_segment_order() {
+        let tenants: [&[u8]; 6] = [b"", b"a", b"a\0", b"a\0\0", b"a\xff", b"ab"];
+        let tables = ["", "t", "t\0", "tt"];
+        let rows = [i64::MIN, -1, 0, 1, i64::MAX];
+        let mut tuples = Vec::new();
+        for tenant in tenants.iter() {
+            for table in tables.iter() {
+                for row in rows.iter() {
+                    tuples.push((tenant.to_vec(), table.to_string(), *row));
+                }
+            }
+        }
+        let mut keys: Vec<Vec<u8>> = tuples
+            .iter()
+            .map(|(tenant, table, row)| _key(tenant, table, *row))
+            .collect();
+        tuples.sort();
+        keys.sort();
+
+        for (tuple, key) in tuples.iter().zip(keys.iter()) {
+            let mut reader = KeyReader::new(key);
+            assert_eq!(reader.read_bytes().as_ref(), Some(&tuple.0));
+            assert_eq!(reader.read_str().as_ref(), Some(&tuple.1));
+            assert_eq!(reader.read_i64(), Some(tuple.2));
+            assert!(reader.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_reader() {
+        let segments = vec![
+            Segment::Str("a\0b".to_string()),
+            Segment::Bytes(vec![0, 0xff, 0]),
+            Segment::U64(u64::MAX),
+            Segment::I64(-5),
+            Segment::Bytes(Vec::new()),
+        ];
+        let key = segments
+            .iter()
+            .fold(KeyBuilder::new(), |builder, segment| builder.push(segment));
+        assert_eq!(KeyReader::new(key.as_bytes()).collect::<Vec<_>>(), segments);
+
+        // reading the wrong type leaves the reader in place
+        let mut reader = KeyReader::new(key.as_bytes());
+        assert_eq!(reader.read_bytes(), None);
+        assert_eq!(reader.read_i64(), None);
+        assert_eq!(reader.read_str(), Some("a\0b".to_string()));
+        assert_eq!(reader.read_bytes(), Some(vec![0, 0xff, 0]));
+
+        // a segment without its end, an integer cut short and an unknown type code
+        for malformed in [&[STR, b'a'][..], &[U64, 1, 2], &[0x7f, 1]].iter() {
+            let mut reader = KeyReader::new(malformed);
+            assert_eq!(reader.next(), None);
+            assert!(!reader.is_empty());
+            assert_eq!(reader.remaining(), *malformed);
+            assert_eq!(KeyBuilder::decode(malformed), None);
+        }
+        assert_eq!(KeyBuilder::decode(key.as_bytes()), Some(key));
+    }
+
+    #[test]
+    fn test_prefix_scans() {
+        let mut art = Art::new();
+        let tenants: [&[u8]; 4] = [b"acme", b"acme\0", b"acmecorp", b"acm"];
+        for tenant in tenants.iter() {
+            for table in ["users", "users\0", "user"].iter() {
+                for row in -2..3 {
+                    art.insert(_key(tenant, table, row), row);
+                }
+            }
+        }
+
+        let tenant = KeyBuilder::new().push_bytes(b"acme");
+        let scanned: Vec<Segment> = art
+            .prefix_iter(&tenant)
+            .flat_map(|(key, _)| KeyReader::new(key).take(1))
+            .collect();
+        assert_eq!(scanned.len(), 15);
+        assert!(scanned
+            .iter()
+            .all(|segment| *segment == Segment::Bytes(b"acme".to_vec())));
+
+        let table = tenant.push_str("users");
+        let rows: Vec<i64> = art
+            .prefix_iter(table.as_bytes())
+            .map(|(key, _)| {
+                let mut reader = KeyReader::new(key);
+                assert_eq!(reader.read_bytes(), Some(b"acme".to_vec()));
+                assert_eq!(reader.read_str(), Some("users".to_string()));
+                reader.read_i64().unwrap()
+            })
+            .collect();
+        assert_eq!(rows, vec![-2, -1, 0, 1, 2]);
+
+        let mut typed: Art<KeyBuilder, ()> = Art::new();
+        typed.insert(KeyBuilder::new().push_u64(7).push_str("x"), ());
+        let keys: Vec<KeyBuilder> = typed.iter().decoded().map(|(k, _)| k).collect();
+        assert_eq!(keys, vec![KeyBuilder::new().push_u64(7).push_str("x")]);
+    }
+
+    #[test]
+    fn test_nested_builders() {
+        // built keys of different lengths in front of another element of a tuple
+        let builders = [
+            KeyBuilder::new(),
+            KeyBuilder::new().push_str("a"),
+            KeyBuilder::new().push_str("a").push_u64(0),
+            KeyBuilder::new().push_str("a\0"),
+            KeyBuilder::new().push_str("ab"),
+            KeyBuilder::new().push_bytes(b"").push_i64(-1),
+        ];
+        let mut tuples = Vec::new();
+        for builder in builders.iter() {
+            for last in [0u8, 7, 0xff].iter() {
+                tuples.push((builder.clone(), *last));
+            }
+        }
+
+        for tuple in tuples.iter() {
+            assert_eq!(
+                <(KeyBuilder, u8)>::decode(&tuple.encode()),
+                Some(tuple.clone())
+            );
+        }
+
+        let mut encoded: Vec<Vec<u8>> =
+            tuples.iter().map(|tuple| tuple.encode().to_vec()).collect();
+        encoded.sort();
+        tuples.sort_by(|a, b| (a.0.as_bytes(), a.1).cmp(&(b.0.as_bytes(), b.1)));
+        let decoded: Vec<(KeyBuilder, u8)> = encoded
+            .iter()
+            .map(|bytes| <(KeyBuilder, u8)>::decode(bytes).unwrap())
+            .collect();
+        assert_eq!(decoded, tuples);
+    }
+}
@@ -0,0 +1,653 @@
+use crate::path::NodeRef;
+use crate::{ArtError, ArtKey, Cursor, CursorMut, KeyLookup, Node, Path, RawNode};
+use std::marker::PhantomData;
+use std::mem::replace;
+use std::ptr::NonNull;
+
+impl<'a, K: ArtKey, V> Cursor<'a, K, V> {
+    pub(crate) fn new(root: &'a Node<V>) -> Self {
+        Cursor {
+            root,
+            path: Path::new(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Moves to the first key that is greater than or equal to `key`.
+    pub fn seek<Q: KeyLookup<K> + ?Sized>(&mut self, key: &Q) {
+        self.path.seek(self.root, &key.encode(), true);
+    }
+
+    /// Moves to the last key that is less than or equal to `key`.
+    pub fn seek_for_prev<Q: KeyLookup<K> + ?Sized>(&mut self, key: &Q) {
+        self.path.seek_back(self.root, &key.encode(), true);
+    }
+
+    /// Moves to the smallest key in the tree.
+    pub fn seek_to_first(&mut self) {
+        self.path.first(self.root);
+    }
+
+    /// Moves to the largest key in the tree.
+    pub fn seek_to_last(&mut self) {
+        self.path.last(self.root);
+    }
+
+    /// Steps to the following key, a no-op on an unpositioned cursor.
+    pub fn next(&mut self) {
+        if self.valid() {
+            self.path.next();
+        }
+    }
+
+    /// Steps to the preceding key, a no-op on an unpositioned cursor.
+    pub fn prev(&mut self) {
+        if self.valid() {
+            self.path.prev();
+        }
+    }
+
+    /// Returns whether the cursor points at an entry.
+    pub fn valid(&self) -> bool {
+        self.path.leaf().is_some()
+    }
+
+    /// Returns the key at the current position.
+    pub fn key(&self) -> Option<&'a [u8]> {
+        self.path.leaf().map(|leaf| leaf.key.as_slice())
+    }
+
+    /// Returns the value at the current position.
+    pub fn value(&self) -> Option<&'a V> {
+        self.path.leaf().map(|leaf| &leaf.value)
+    }
+}
+
+impl<'a, K: ArtKey, V> CursorMut<'a, K, V> {
+    pub(crate) fn new(root: &'a mut Node<V>, size: &'a mut usize) -> Self {
+        CursorMut {
+            root: RawNode::new(root),
+            size,
+            path: Path::new(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Moves to the first key that is greater than or equal to `key`.
+    pub fn seek<Q: KeyLookup<K> + ?Sized>(&mut self, key: &Q) {
+        self.path.seek(self.root, &key.encode(), true);
+    }
+
+    /// Moves to the last key that is less than or equal to `key`.
+    pub fn seek_for_prev<Q: KeyLookup<K> + ?Sized>(&mut self, key: &Q) {
+        self.path.seek_back(self.root, &key.encode(), true);
+    }
+
+    /// Moves to the smallest key in the tree.
+    pub fn seek_to_first(&mut self) {
+        self.path.first(self.root);
+    }
+
+    /// Moves to the largest key in the tree.
+    pub fn seek_to_last(&mut self) {
+        self.path.last(self.root);
+    }
+
+    /// Steps to the following key, a no-op on an unpositioned cursor.
+    pub fn next(&mut self) {
+        if self.valid() {
+            self.path.next();
+        }
+    }
+
+    /// Steps to the preceding key, a no-op on an unpositioned cursor.
+    pub fn prev(&mut self) {
+        if self.valid() {
+            self.path.prev();
+        }
+    }
+
+    /// Returns whether the cursor points at an entry.
+    pub fn valid(&self) -> bool {
+        self.path.leaf().is_some()
+    }
+
+    /// Returns the key at the current position.
+    pub fn key(&self) -> Option<&[u8]> {
+        self.path.leaf().map(|leaf| leaf.key.as_slice())
+    }
+
+    /// Returns the value at the current position.
+    pub fn value(&self) -> Option<&V> {
+        self.path.leaf().map(|leaf| &leaf.value)
+    }
+
+    /// Returns the value at the current position for updating it in place.
+    pub fn value_mut(&mut self) -> Option<&mut V> {
+        // the value is borrowed along with the cursor, so the path can't be walked while it is
+        // in use
+        match unsafe { self.path.leaf_ref()?.node_mut() } {
+            Node::Leaf(leaf) => Some(&mut leaf.value),
+            _ => None,
+        }
+    }
+
+    /// Removes the entry at the current position and moves on to the following key.
+    ///
+    /// Panics where `try_remove_current` reports an error.
+    pub fn remove_current(&mut self) -> Option<(Vec<u8>, V)> {
+        self.try_remove_current()
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Removes the entry at the current position and moves on to the following key, or
+    /// returns the error that kept it from being removed, leaving the cursor unpositioned.
+    pub fn try_remove_current(&mut self) -> Result<Option<(Vec<u8>, V)>, ArtError> {
+        let key = match self.path.leaf() {
+            Some(leaf) => leaf.key.clone(),
+            None => return Ok(None),
+        };
+        let removed = match self.path.pop() {
+            Some((node, depth)) => {
+                // the parent of the leaf drops it and shrinks in place, everything above it
+                // stays as it was so the traversal can resume from there. The nodes below the
+                // parent are off the path, and it is walked again only once the parent is done
+                // with.
+                let parent = unsafe { node.node_mut() };
+                let key_char = key.get(depth + parent.prefix_len()).copied();
+                let removed = match parent.remove_child(key_char) {
+                    Ok(removed) => removed,
+                    Err(err) => {
+                        self.path.clear();
+                        return Err(err);
+                    }
+                };
+                self.path.seek_from(node, depth, &key, false);
+                removed
+            }
+            None => {
+                self.path.clear();
+                // the path is cleared, nothing else reaches the root
+                Some(replace(unsafe { self.root.node_mut() }, Node::None))
+            }
+        };
+
+        match removed {
+            Some(Node::Leaf(leaf)) => {
+                *self.size -= 1;
+                Ok(Some((leaf.key, leaf.value)))
+            }
+            _ => {
+                self.path.clear();
+                Err(ArtError::Corrupted("expected a leaf"))
+            }
+        }
+    }
+
+    /// Inserts an entry for a key that sorts after the current one, replacing the value if the
+    /// key is already present. The cursor stays on the current entry so that `next` moves on
+    /// to the new one when nothing lies in between.
+    ///
+    /// Panics where `try_insert_after` reports an error, which includes a `key` that is not
+    /// greater than the current key.
+    pub fn insert_after(&mut self, key: K, value: V) {
+        self.try_insert_after(key, value)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Inserts an entry for a key that sorts after the current one like `insert_after` does,
+    /// or returns the error that kept it from being inserted, `ArtError::KeyOutOfOrder` when
+    /// `key` is not greater than the current key. The cursor stays on the current entry either
+    /// way.
+    pub fn try_insert_after(&mut self, key: K, value: V) -> Result<(), ArtError> {
+        let key = key.into_encoded();
+        let current = match self.path.leaf() {
+            Some(leaf) => leaf.key.clone(),
+            None => {
+                // the path is empty, nothing else reaches the root
+                let root = unsafe { self.root.node_mut() };
+                let (_, old_value) = Node::insert_at(root, 0, key, value)?;
+                if old_value.is_none() {
+                    *self.size += 1;
+                }
+                return Ok(());
+            }
+        };
+        if key <= current {
+            return Err(ArtError::KeyOutOfOrder);
+        }
+
+        // the deepest node on the path that the new key shares the bytes leading to
+        let common = current
+            .iter()
+            .zip(key.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let (node, depth) = loop {
+            match self.path.pop() {
+                Some((node, depth)) if depth <= common => break (node, depth),
+                Some(_) => {}
+                None => break (self.root, 0),
+            }
+        };
+
+        // the nodes below are off the path, which is walked again once the insert is done
+        let inserted = Node::insert_at(unsafe { node.node_mut() }, depth, key, value)
+            .map(|(_, old_value)| old_value);
+        self.path.seek_from(node, depth, &current, true);
+        if inserted?.is_none() {
+            *self.size += 1;
+        }
+        Ok(())
+    }
+}
+
+impl<'a, V> RawNode<'a, V> {
+    pub(crate) fn new(node: &'a mut Node<V>) -> Self {
+        RawNode {
+            ptr: NonNull::from(node),
+            marker: PhantomData,
+        }
+    }
+
+    /// Borrows the node mutably for as long as the tree is borrowed.
+    ///
+    /// # Safety
+    ///
+    /// The handle has to come from `new` or from a walk below it, and the node must not have
+    /// been moved or dropped since, which modifying one of its ancestors can do. For as long as
+    /// the returned reference is used, nothing else may reach the node or anything below it:
+    /// no other reference `node_mut` or `NodeRef::node` returned for it, and no walk of a path
+    /// through it. So either the reference ends before the path is walked again, as with the
+    /// cursor, or the path has left the node behind for good, as with the leaves `IterMut`
+    /// hands out.
+    pub(crate) unsafe fn node_mut(self) -> &'a mut Node<V> {
+        &mut *self.ptr.as_ptr()
+    }
+
+    fn with_ptr(self, ptr: NonNull<Node<V>>) -> Self {
+        RawNode {
+            ptr,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, V> Clone for RawNode<'a, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, V> Copy for RawNode<'a, V> {}
+
+impl<'a, V> NodeRef<'a> for RawNode<'a, V> {
+    type Value = V;
+
+    // the walk only reads the node it stands on, which no reference from node_mut reaches
+    // while the path is in use
+    fn node(self) -> &'a Node<V> {
+        unsafe { &*self.ptr.as_ptr() }
+    }
+
+    // the pointers come straight out of the node's fields, the walk never borrows the entries
+    // it passes over, see node_mut
+    fn next_entry(self, from: usize) -> Option<(usize, Self)> {
+        let (position, ptr) = unsafe { Node::next_entry_ptr(self.ptr, from) }?;
+        Some((position, self.with_ptr(ptr)))
+    }
+
+    fn prev_entry(self, before: usize) -> Option<(usize, Self)> {
+        let (position, ptr) = unsafe { Node::prev_entry_ptr(self.ptr, before) }?;
+        Some((position, self.with_ptr(ptr)))
+    }
+
+    fn seek_child(self, key_char: u8) -> (usize, Option<Self>) {
+        let (position, ptr) = unsafe { Node::seek_child_ptr(self.ptr, key_char) };
+        (position, ptr.map(|ptr| self.with_ptr(ptr)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Art, ArtError};
+    use std::collections::BTreeMap;
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+    use std::ops::Bound::{Excluded, Included, Unbounded};
+
+    #[test]
+    fn test_cursor_empty() {
+        let art: Art<Vec<u8>, ()> = Art::new();
+        let mut cursor = art.cursor();
+        assert!(!cursor.valid());
+        cursor.seek(b"A");
+        assert_eq!(cursor.key(), None);
+        cursor.seek_to_last();
+        assert_eq!(cursor.value(), None);
+        cursor.next();
+        assert!(!cursor.valid());
+    }
+
+    #[test]
+    fn test_cursor_seek() {
+        let mut art = Art::new();
+        let items = ["A", "AMD", "AMDs", "Ab", "Congregationalist", "Dong"];
+        for (i, item) in items.iter().enumerate() {
+            art.insert(item.as_bytes().to_vec(), i);
+        }
+
+        let mut cursor = art.cursor();
+        cursor.seek(b"AM");
+        assert_eq!(cursor.key(), Some("AMD".as_bytes()));
+        assert_eq!(cursor.value(), Some(&1));
+        cursor.next();
+        assert_eq!(cursor.key(), Some("AMDs".as_bytes()));
+        cursor.prev();
+        cursor.prev();
+        assert_eq!(cursor.key(), Some("A".as_bytes()));
+        cursor.prev();
+        assert!(!cursor.valid());
+        // stays unpositioned until seeked again
+        cursor.next();
+        assert!(!cursor.valid());
+
+        cursor.seek_for_prev(b"Congregation");
+        assert_eq!(cursor.key(), Some("Ab".as_bytes()));
+        cursor.seek_for_prev(b"Congregationalist");
+        assert_eq!(cursor.key(), Some("Congregationalist".as_bytes()));
+        cursor.seek(b"Congregationalists");
+        assert_eq!(cursor.key(), Some("Dong".as_bytes()));
+        cursor.next();
+        assert_eq!(cursor.key(), None);
+        cursor.seek(b"E");
+        assert_eq!(cursor.key(), None);
+        cursor.seek_for_prev(b"");
+        assert_eq!(cursor.key(), None);
+
+        cursor.seek_to_first();
+        assert_eq!(cursor.key(), Some("A".as_bytes()));
+        cursor.seek_to_last();
+        assert_eq!(cursor.key(), Some("Dong".as_bytes()));
+    }
+
+    #[test]
+    fn test_cursor_words() {
+        let f_name = "/usr/share/dict/words";
+        let mut art = Art::new();
+        let mut expected = BTreeMap::new();
+        let reader = BufReader::new(File::open(f_name).unwrap());
+        for line in reader.lines().map_while(Result::ok) {
+            let key = line.trim().as_bytes().to_vec();
+            art.insert(key.clone(), ());
+            expected.insert(key, ());
+        }
+
+        let mut cursor = art.cursor();
+        for word in expected.keys().step_by(499) {
+            // a key just before the word and one just after it
+            let before = &word[..word.len() - 1];
+            let mut after = word.clone();
+            after.push(0);
+
+            cursor.seek(before);
+            let matching = expected.range::<[u8], _>((Included(before), Unbounded));
+            assert_eq!(cursor.key(), matching.map(|(k, _)| k.as_slice()).next());
+
+            cursor.seek_for_prev(&after);
+            assert_eq!(cursor.key(), Some(word.as_slice()));
+            let mut matching = expected.range::<[u8], _>((Unbounded, Included(word.as_slice())));
+            for _ in 0..5 {
+                assert_eq!(
+                    cursor.key(),
+                    matching.next_back().map(|(k, _)| k.as_slice())
+                );
+                cursor.prev();
+            }
+
+            cursor.seek(word);
+            let mut matching = expected.range::<[u8], _>((Included(word.as_slice()), Unbounded));
+            for _ in 0..5 {
+                assert_eq!(cursor.key(), matching.next().map(|(k, _)| k.as_slice()));
+                cursor.next();
+            }
+        }
+    }
+
+    #[test]
+    fn test_cursor_mut_value() {
+        let mut art = Art::new();
+        for item in ["A", "AMD", "AMDs", "Ab"].iter() {
+            art.insert(item.as_bytes().to_vec(), 0);
+        }
+
+        let mut cursor = art.cursor_mut();
+        assert_eq!(cursor.value_mut(), None);
+        cursor.seek(b"AM");
+        while let Some(value) = cursor.value_mut() {
+            *value += 1;
+            cursor.next();
+        }
+        cursor.seek_to_first();
+        *cursor.value_mut().unwrap() = 10;
+
+        let values: Vec<&i32> = art.values().collect();
+        assert_eq!(values, vec![&10, &1, &1, &1]);
+    }
+
+    #[test]
+    fn test_cursor_mut_remove_shrink() {
+        // empty out a node of every size from both ends, a key at a time
+        for count in [3usize, 10, 40, 256].iter() {
+            let mut art = Art::new();
+            let mut expected = BTreeMap::new();
+            for byte in 0..*count {
+                art.insert(vec![b'k', byte as u8], byte);
+                expected.insert(vec![b'k', byte as u8], byte);
+            }
+            art.insert(vec![b'k'], 1000);
+            expected.insert(vec![b'k'], 1000);
+            art.insert(vec![b'l'], 2000);
+            expected.insert(vec![b'l'], 2000);
+
+            let mut cursor = art.cursor_mut();
+            cursor.seek(b"k");
+            let mut removed = 0;
+            while cursor.key() != Some(b"l") {
+                let key = cursor.key().unwrap().to_vec();
+                // drop every other key on the way forward
+                if removed % 2 == 0 {
+                    let value = expected.remove(&key);
+                    assert_eq!(cursor.remove_current(), Some((key, value.unwrap())));
+                } else {
+                    cursor.next();
+                }
+                removed += 1;
+            }
+            // then the rest from the back
+            loop {
+                cursor.seek_for_prev(&[b'k', 255]);
+                let key = match cursor.key() {
+                    Some(key) => key.to_vec(),
+                    None => break,
+                };
+                expected.remove(&key);
+                assert_eq!(cursor.remove_current().unwrap().0, key);
+                assert_eq!(cursor.key(), Some("l".as_bytes()));
+            }
+            cursor.seek_to_first();
+            expected.remove(&vec![b'l']);
+            assert_eq!(cursor.remove_current(), Some((vec![b'l'], 2000)));
+            assert!(!cursor.valid());
+
+            assert!(expected.is_empty());
+            assert_eq!(art.len(), 0);
+            assert_eq!(art.iter().next(), None);
+        }
+    }
+
+    #[test]
+    fn test_cursor_mut_root_leaf() {
+        let mut art: Art<Vec<u8>, i32> = Art::new();
+        let mut cursor = art.cursor_mut();
+        cursor.insert_after(b"A".to_vec(), 1);
+        cursor.seek_to_first();
+        cursor.insert_after(b"B".to_vec(), 2);
+        assert_eq!(cursor.key(), Some("A".as_bytes()));
+        assert_eq!(cursor.remove_current(), Some((b"A".to_vec(), 1)));
+        assert_eq!(cursor.key(), Some("B".as_bytes()));
+        assert_eq!(cursor.remove_current(), Some((b"B".to_vec(), 2)));
+        assert_eq!(cursor.remove_current(), None);
+        assert!(art.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_cursor_mut_insert_before() {
+        let mut art = Art::new();
+        art.insert(b"B".to_vec(), ());
+        let mut cursor = art.cursor_mut();
+        cursor.seek_to_first();
+        cursor.insert_after(b"A".to_vec(), ());
+    }
+
+    #[test]
+    fn test_cursor_mut_try_insert_before() {
+        let mut art = Art::new();
+        art.insert(b"B".to_vec(), ());
+        let mut cursor = art.cursor_mut();
+        cursor.seek_to_first();
+        for key in [&b"A"[..], b"B"].iter() {
+            assert_eq!(
+                cursor.try_insert_after(key.to_vec(), ()),
+                Err(ArtError::KeyOutOfOrder)
+            );
+            assert_eq!(cursor.key(), Some(&b"B"[..]));
+        }
+        assert_eq!(cursor.try_insert_after(b"C".to_vec(), ()), Ok(()));
+        assert_eq!(art.len(), 2);
+    }
+
+    #[test]
+    fn test_cursor_mut_words() {
+        let f_name = "/usr/share/dict/words";
+        let mut art = Art::new();
+        let mut expected = BTreeMap::new();
+        let reader = BufReader::new(File::open(f_name).unwrap());
+        for line in reader.lines().map_while(Result::ok).step_by(7) {
+            let key = line.trim().as_bytes().to_vec();
+            art.insert(key.clone(), 0);
+            expected.insert(key, 0);
+        }
+
+        // remove, rewrite and update entries while walking forward, checking the position
+        // against the reference after every step
+        let mut cursor = art.cursor_mut();
+        cursor.seek_to_first();
+        let mut step = 0;
+        while let Some(key) = cursor.key().map(|k| k.to_vec()) {
+            match step % 3 {
+                0 => {
+                    let value = expected.remove(&key).unwrap();
+                    assert_eq!(cursor.remove_current(), Some((key.clone(), value)));
+                    let next = expected.range::<[u8], _>((Excluded(key.as_slice()), Unbounded));
+                    assert_eq!(cursor.key(), next.map(|(k, _)| k.as_slice()).next());
+                }
+                1 => {
+                    let mut rewritten = key.clone();
+                    rewritten.extend_from_slice(b"~x");
+                    cursor.insert_after(rewritten.clone(), 1);
+                    expected.insert(rewritten.clone(), 1);
+                    assert_eq!(cursor.key(), Some(key.as_slice()));
+                    cursor.next();
+                    let next = expected.range::<[u8], _>((Excluded(key.as_slice()), Unbounded));
+                    assert_eq!(cursor.key(), next.map(|(k, _)| k.as_slice()).next());
+                    // skip past the rewritten key
+                    while cursor.key() <= Some(rewritten.as_slice()) && cursor.valid() {
+                        cursor.next();
+                    }
+                }
+                _ => {
+                    *cursor.value_mut().unwrap() += 2;
+                    *expected.get_mut(&key).unwrap() += 2;
+                    cursor.next();
+                }
+            }
+            step += 1;
+        }
+
+        assert_eq!(art.len(), expected.len());
+        let items: Vec<(&[u8], &i32)> = art.iter().collect();
+        let expected: Vec<(&[u8], &i32)> =
+            expected.iter().map(|(k, v)| (k.as_slice(), v)).collect();
+        assert_eq!(items, expected);
+    }
+
+    #[test]
+    fn test_raw_nodes() {
+        // small enough for `cargo miri test`, which checks that the pointers the walks take
+        // through RawNode don't invalidate one another or the values handed out
+        let mut art = Art::new();
+        let mut expected = BTreeMap::new();
+        for (stem, count) in [
+            (&b"a"[..], 5u8),
+            (b"k", 30),
+            (b"long/shared/prefix/", 7),
+            (b"m", 200),
+        ]
+        .iter()
+        {
+            for byte in 0..*count {
+                let mut key = stem.to_vec();
+                key.push(byte.wrapping_mul(7));
+                art.insert(key.clone(), byte as usize);
+                expected.insert(key, byte as usize);
+            }
+            art.insert(stem.to_vec(), 1000);
+            expected.insert(stem.to_vec(), 1000);
+        }
+
+        // every value IterMut hands out stays usable after the walk moved on
+        let values: Vec<&mut usize> = art.iter_mut().map(|(_, value)| value).collect();
+        for value in values {
+            *value += 1;
+        }
+        for value in expected.values_mut() {
+            *value += 1;
+        }
+
+        let mut cursor = art.cursor_mut();
+        cursor.seek(b"b");
+        let mut step = 0;
+        while let Some(key) = cursor.key().map(|k| k.to_vec()) {
+            match step % 3 {
+                0 => {
+                    let value = expected.remove(&key).unwrap();
+                    assert_eq!(cursor.remove_current(), Some((key, value)));
+                }
+                1 => {
+                    let mut after = key.clone();
+                    after.push(0);
+                    cursor.insert_after(after.clone(), 0);
+                    expected.insert(after, 0);
+                    cursor.next();
+                    cursor.next();
+                }
+                _ => {
+                    *cursor.value_mut().unwrap() += 2;
+                    *expected.get_mut(&key).unwrap() += 2;
+                    cursor.next();
+                }
+            }
+            step += 1;
+        }
+        cursor.seek_for_prev(b"long/shared/prefix/~");
+        let last = expected.range::<[u8], _>((Unbounded, Included(&b"long/shared/prefix/~"[..])));
+        assert_eq!(cursor.key(), last.map(|(k, _)| k.as_slice()).next_back());
+
+        let items: Vec<(&[u8], &usize)> = art.iter().collect();
+        let expected: Vec<(&[u8], &usize)> =
+            expected.iter().map(|(k, v)| (k.as_slice(), v)).collect();
+        assert_eq!(items, expected);
+    }
+}
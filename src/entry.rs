@@ -0,0 +1,444 @@
+use crate::{ArtError, ArtKey, Entry, Node, OccupiedEntry, VacantEntry};
+use std::mem::replace;
+
+impl<'a, K: ArtKey, V> Entry<'a, K, V> {
+    /// Inserts `default` if the entry is vacant, and returns a mutable reference to the value.
+    ///
+    /// Panics where `try_or_insert` reports an error.
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.try_or_insert(default)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Inserts `default` if the entry is vacant, and returns a mutable reference to the value,
+    /// or the error that kept it from being found or inserted.
+    pub fn try_or_insert(self, default: V) -> Result<&'a mut V, ArtError> {
+        self.try_or_insert_with(|| default)
+    }
+
+    /// Inserts the result of `default` if the entry is vacant, and returns a mutable reference
+    /// to the value.
+    ///
+    /// Panics where `try_or_insert_with` reports an error.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        self.try_or_insert_with(default)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Inserts the result of `default` if the entry is vacant, and returns a mutable reference
+    /// to the value, or the error that kept it from being found or inserted.
+    pub fn try_or_insert_with<F: FnOnce() -> V>(self, default: F) -> Result<&'a mut V, ArtError> {
+        match self {
+            Entry::Occupied(entry) => entry.try_into_mut(),
+            Entry::Vacant(entry) => entry.try_insert(default()),
+        }
+    }
+
+    /// Inserts the result of calling `default` with the key if the entry is vacant, and returns
+    /// a mutable reference to the value.
+    ///
+    /// Panics where `try_or_insert_with_key` reports an error.
+    pub fn or_insert_with_key<F: FnOnce(&K) -> V>(self, default: F) -> &'a mut V {
+        self.try_or_insert_with_key(default)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Inserts the result of calling `default` with the key if the entry is vacant, and returns
+    /// a mutable reference to the value, or the error that kept it from being found or inserted.
+    pub fn try_or_insert_with_key<F: FnOnce(&K) -> V>(
+        self,
+        default: F,
+    ) -> Result<&'a mut V, ArtError> {
+        match self {
+            Entry::Occupied(entry) => entry.try_into_mut(),
+            Entry::Vacant(entry) => {
+                let value = default(&entry.key);
+                entry.try_insert(value)
+            }
+        }
+    }
+
+    /// Returns the key of the entry.
+    pub fn key(&self) -> &K {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Calls `f` on the value of an occupied entry before any potential inserts.
+    ///
+    /// Panics where `OccupiedEntry::try_get_mut` reports an error.
+    pub fn and_modify<F: FnOnce(&mut V)>(self, f: F) -> Self {
+        match self {
+            Entry::Occupied(mut entry) => {
+                f(entry.get_mut());
+                Entry::Occupied(entry)
+            }
+            Entry::Vacant(entry) => Entry::Vacant(entry),
+        }
+    }
+}
+
+impl<'a, K: ArtKey, V: Default> Entry<'a, K, V> {
+    /// Inserts the default value if the entry is vacant, and returns a mutable reference to the
+    /// value.
+    ///
+    /// Panics where `try_or_default` reports an error.
+    pub fn or_default(self) -> &'a mut V {
+        self.try_or_default()
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Inserts the default value if the entry is vacant, and returns a mutable reference to the
+    /// value, or the error that kept it from being found or inserted.
+    pub fn try_or_default(self) -> Result<&'a mut V, ArtError> {
+        self.try_or_insert_with(Default::default)
+    }
+}
+
+impl<'a, K: ArtKey, V> VacantEntry<'a, K, V> {
+    /// Returns the key that would be used when inserting through the entry.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Takes ownership of the key.
+    pub fn into_key(self) -> K {
+        self.key
+    }
+
+    /// Inserts `value` under the key of the entry, and returns a mutable reference to it.
+    ///
+    /// Panics where `try_insert` reports an error.
+    pub fn insert(self, value: V) -> &'a mut V {
+        self.try_insert(value)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Inserts `value` under the key of the entry, and returns a mutable reference to it, or
+    /// the error that kept it from being inserted.
+    pub fn try_insert(self, value: V) -> Result<&'a mut V, ArtError> {
+        let VacantEntry {
+            key,
+            encoded,
+            node,
+            depth,
+            size,
+        } = self;
+        let encoded = encoded.unwrap_or_else(|| key.into_encoded());
+        let (value, _) = Node::insert_at(node, depth, encoded, value)?;
+        *size += 1;
+        Ok(value)
+    }
+}
+
+impl<'a, K: ArtKey, V> OccupiedEntry<'a, K, V> {
+    /// Returns the key of the entry.
+    pub fn key(&self) -> &K {
+        &self.key
+    }
+
+    /// Returns a reference to the value in the entry.
+    ///
+    /// Panics where `try_get` reports an error.
+    pub fn get(&self) -> &V {
+        self.try_get().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Returns a reference to the value in the entry, or an error if the leaf it was found at
+    /// is gone.
+    pub fn try_get(&self) -> Result<&V, ArtError> {
+        let leaf = match self.key_char {
+            Some(key_char) => self.node.child(key_char),
+            None => Some(&*self.node),
+        };
+        match leaf {
+            Some(Node::Leaf(leaf)) => Ok(&leaf.value),
+            _ => Err(ArtError::Corrupted("expected a leaf")),
+        }
+    }
+
+    /// Returns a mutable reference to the value in the entry.
+    ///
+    /// Panics where `try_get_mut` reports an error.
+    pub fn get_mut(&mut self) -> &mut V {
+        self.try_get_mut().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Returns a mutable reference to the value in the entry, or an error if the leaf it was
+    /// found at is gone.
+    pub fn try_get_mut(&mut self) -> Result<&mut V, ArtError> {
+        let leaf = match self.key_char {
+            Some(key_char) => self.node.child_mut(key_char),
+            None => Some(&mut *self.node),
+        };
+        Node::value_mut(leaf)
+    }
+
+    /// Converts the entry into a mutable reference to its value.
+    ///
+    /// Panics where `try_into_mut` reports an error.
+    pub fn into_mut(self) -> &'a mut V {
+        self.try_into_mut().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Converts the entry into a mutable reference to its value, or an error if the leaf it
+    /// was found at is gone.
+    pub fn try_into_mut(self) -> Result<&'a mut V, ArtError> {
+        let leaf = match self.key_char {
+            Some(key_char) => self.node.child_mut(key_char),
+            None => Some(self.node),
+        };
+        Node::value_mut(leaf)
+    }
+
+    /// Sets the value of the entry, and returns the old value.
+    ///
+    /// Panics where `try_insert` reports an error.
+    pub fn insert(&mut self, value: V) -> V {
+        self.try_insert(value)
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Sets the value of the entry, and returns the old value, or an error if the leaf it was
+    /// found at is gone.
+    pub fn try_insert(&mut self, value: V) -> Result<V, ArtError> {
+        Ok(replace(self.try_get_mut()?, value))
+    }
+
+    /// Takes the value out of the entry, and returns it.
+    ///
+    /// Panics where `try_remove` reports an error.
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+
+    /// Takes the value out of the entry, and returns it, or the error that kept it from being
+    /// removed.
+    pub fn try_remove(self) -> Result<V, ArtError> {
+        self.try_remove_entry().map(|(_, value)| value)
+    }
+
+    /// Takes the key and value out of the entry, shrinking the parent the same way
+    /// `Art::remove` does.
+    ///
+    /// Panics where `try_remove_entry` reports an error.
+    pub fn remove_entry(self) -> (K, V) {
+        self.try_remove_entry()
+            .unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Takes the key and value out of the entry, or returns the error that kept them from
+    /// being removed.
+    pub fn try_remove_entry(self) -> Result<(K, V), ArtError> {
+        let removed = match self.key_char {
+            Some(key_char) => self.node.remove_child(key_char)?,
+            None => Some(replace(self.node, Node::None)),
+        };
+        match removed {
+            Some(Node::Leaf(leaf)) => {
+                *self.size -= 1;
+                Ok((self.key, leaf.value))
+            }
+            _ => Err(ArtError::Corrupted("expected a leaf")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Art, ArtError, Entry, Node, Node4};
+    use std::collections::BTreeMap;
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    #[test]
+    fn test_entry_split_cases() {
+        let mut art = Art::new();
+        // empty tree, then a root leaf split into a node4
+        *art.entry(b"Congregationalist".to_vec()).or_insert(0) += 1;
+        *art.entry(b"Congregationalists".to_vec()).or_insert(0) += 1;
+        // the key branches off inside a prefix longer than MAX_PREFIX
+        *art.entry(b"Congo".to_vec()).or_insert(0) += 1;
+        // a leaf below a node gets split
+        *art.entry(b"Congox".to_vec()).or_insert(0) += 1;
+        // a new child on an existing node
+        *art.entry(b"Congregationalist's".to_vec()).or_insert(0) += 1;
+        *art.entry(b"Congo".to_vec()).or_insert(0) += 1;
+        *art.entry(b"Congregationalist".to_vec()).or_insert(0) += 1;
+
+        assert_eq!(art.len(), 5);
+        let items: Vec<(&[u8], &i32)> = art.iter().collect();
+        assert_eq!(
+            items,
+            vec![
+                ("Congo".as_bytes(), &2),
+                ("Congox".as_bytes(), &1),
+                ("Congregationalist".as_bytes(), &2),
+                ("Congregationalist's".as_bytes(), &1),
+                ("Congregationalists".as_bytes(), &1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_entry_variants() {
+        let mut art = Art::new();
+        art.insert(b"A".to_vec(), 1);
+
+        match art.entry(b"A".to_vec()) {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.key(), b"A");
+                assert_eq!(entry.get(), &1);
+                assert_eq!(entry.insert(2), 1);
+            }
+            Entry::Vacant(_) => panic!("A is present"),
+        }
+        match art.entry(b"B".to_vec()) {
+            Entry::Vacant(entry) => {
+                assert_eq!(entry.key(), b"B");
+                assert_eq!(entry.into_key(), b"B".to_vec());
+            }
+            Entry::Occupied(_) => panic!("B is not present"),
+        }
+        assert_eq!(art.len(), 1);
+
+        art.entry(b"A".to_vec())
+            .and_modify(|v| *v += 10)
+            .or_insert(0);
+        art.entry(b"B".to_vec())
+            .and_modify(|v| *v += 10)
+            .or_insert(0);
+        assert_eq!(art.entry(b"C".to_vec()).or_insert_with(|| 7), &7);
+        assert_eq!(
+            art.entry(b"A".to_vec()).or_insert_with(|| unreachable!()),
+            &12
+        );
+        assert_eq!(art.entry(b"B".to_vec()).key(), b"B");
+        assert_eq!(*art.entry(b"D".to_vec()).or_default(), 0);
+        let key_len = art
+            .entry(b"EE".to_vec())
+            .or_insert_with_key(|key| key.len() as i32);
+        assert_eq!(*key_len, 2);
+
+        assert_eq!(art.entry(b"F".to_vec()).try_or_default(), Ok(&mut 0));
+        assert_eq!(
+            art.entry(b"GGG".to_vec())
+                .try_or_insert_with_key(|key| key.len() as i32),
+            Ok(&mut 3)
+        );
+        match art.entry(b"GGG".to_vec()) {
+            Entry::Occupied(mut entry) => {
+                assert_eq!(entry.try_insert(4), Ok(3));
+                assert_eq!(entry.try_remove(), Ok(4));
+            }
+            Entry::Vacant(_) => panic!("GGG is present"),
+        }
+        assert_eq!(art.len(), 6);
+    }
+
+    #[test]
+    fn test_entry_typed_keys() {
+        let mut art: Art<String, usize> = Art::new();
+        art.insert("a".to_string(), 1);
+        match art.entry("b".to_string()) {
+            Entry::Vacant(entry) => {
+                assert_eq!(entry.key(), "b");
+                assert_eq!(entry.into_key(), "b".to_string());
+            }
+            Entry::Occupied(_) => panic!("b is not present"),
+        }
+        let len = art
+            .entry("abc".to_string())
+            .or_insert_with_key(|key| key.len());
+        assert_eq!(*len, 3);
+        match art.entry("a".to_string()) {
+            Entry::Occupied(entry) => {
+                assert_eq!(entry.key(), "a");
+                assert_eq!(entry.remove_entry(), ("a".to_string(), 1));
+            }
+            Entry::Vacant(_) => panic!("a is present"),
+        }
+        assert_eq!(art.len(), 1);
+
+        // a key whose encoding is built for the descent goes in with those bytes
+        let mut tuples: Art<(String, u32), u32> = Art::new();
+        for (name, id) in [("b", 2), ("a", 1), ("a\0", 3), ("a", 1), ("a", 0)].iter() {
+            *tuples.entry((name.to_string(), *id)).or_insert(0) += id;
+        }
+        assert_eq!(tuples.len(), 4);
+        assert_eq!(tuples.search(&("a".to_string(), 1)), Some(&2));
+        assert_eq!(tuples.search(&("a\0".to_string(), 3)), Some(&3));
+    }
+
+    #[test]
+    fn test_failed_insert_keeps_len() {
+        // an inner node whose compressed path is longer than `partial` but has no leaves to
+        // read the rest of it from, so the prefix can't be split
+        let mut node4: Node4<usize> = Node4::new();
+        node4.meta.prefix_len = 12;
+        node4.meta.partial = b"abcdefgh".to_vec();
+        let mut art = Art::new();
+        art.root = Node::Node4(node4);
+        art.size = 1;
+
+        let inserted = art
+            .try_entry(b"abcdXY".to_vec())
+            .and_then(|entry| entry.try_or_insert(1).map(|value| *value));
+        assert!(matches!(inserted, Err(ArtError::Corrupted(_))));
+        assert_eq!(art.len(), 1);
+
+        let inserted = catch_unwind(AssertUnwindSafe(|| {
+            art.entry(b"abcdXY".to_vec()).or_insert(1);
+        }));
+        assert!(inserted.is_err());
+        assert_eq!(art.len(), 1);
+    }
+
+    #[test]
+    fn test_entry_remove() {
+        // removing through entries shrinks the nodes like Art::remove
+        let mut art = Art::new();
+        for byte in 0..=255u8 {
+            art.insert(vec![b'k', byte], byte);
+        }
+        art.insert(vec![b'k'], 0);
+        for byte in 0..=255u8 {
+            match art.entry(vec![b'k', byte]) {
+                Entry::Occupied(entry) => {
+                    assert_eq!(entry.remove_entry(), (vec![b'k', byte], byte))
+                }
+                Entry::Vacant(_) => panic!("{} is present", byte),
+            }
+        }
+        assert_eq!(art.len(), 1);
+        match art.entry(vec![b'k']) {
+            Entry::Occupied(entry) => assert_eq!(entry.remove(), 0),
+            Entry::Vacant(_) => panic!("k is present"),
+        }
+        assert!(art.is_empty());
+        assert_eq!(art.iter().next(), None);
+    }
+
+    #[test]
+    fn test_entry_words() {
+        let f_name = "/usr/share/dict/words";
+        let mut art = Art::new();
+        let mut expected = BTreeMap::new();
+        let reader = BufReader::new(File::open(f_name).unwrap());
+        // count the first four letters of every word
+        for line in reader.lines().map_while(Result::ok) {
+            let key: Vec<u8> = line.trim().bytes().take(4).collect();
+            *expected.entry(key.clone()).or_insert(0) += 1;
+            *art.entry(key).or_insert(0) += 1;
+        }
+
+        assert_eq!(art.len(), expected.len());
+        let items: Vec<(&[u8], &usize)> = art.iter().collect();
+        let expected: Vec<(&[u8], &usize)> =
+            expected.iter().map(|(k, v)| (k.as_slice(), v)).collect();
+        assert_eq!(items, expected);
+    }
+}
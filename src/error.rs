@@ -0,0 +1,30 @@
+use crate::{ArtError, RegexError};
+use std::collections::TryReserveError;
+use std::error::Error;
+use std::fmt::{Display, Formatter};
+
+impl Display for ArtError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        match self {
+            ArtError::Corrupted(reason) => write!(f, "tree is corrupted: {}", reason),
+            ArtError::AllocationFailed => write!(f, "allocation failed"),
+            ArtError::KeyOutOfOrder => write!(f, "key has to sort after the current key"),
+        }
+    }
+}
+
+impl Error for ArtError {}
+
+impl Display for RegexError {
+    fn fmt(&self, f: &mut Formatter) -> Result<(), std::fmt::Error> {
+        write!(f, "invalid regex at {}: {}", self.position, self.reason)
+    }
+}
+
+impl Error for RegexError {}
+
+impl From<TryReserveError> for ArtError {
+    fn from(_: TryReserveError) -> Self {
+        ArtError::AllocationFailed
+    }
+}
@@ -0,0 +1,698 @@
+use crate::{
+    ArtKey, Decoded, DecodedKeys, IntoIter, Iter, IterMut, Keys, Node, Path, Prefixes, RawNode,
+    Values, MAX_PREFIX,
+};
+use std::borrow::Cow;
+use std::cmp::min;
+use std::marker::PhantomData;
+use std::mem::replace;
+use std::ops::Bound;
+use std::ptr;
+
+impl<'a, K, V> Iter<'a, K, V> {
+    pub(crate) fn new(root: &'a Node<V>) -> Self {
+        Iter::range(root, Bound::Unbounded, Bound::Unbounded)
+    }
+
+    pub(crate) fn empty() -> Self {
+        Iter {
+            front: Path::new(),
+            back: Path::new(),
+            marker: PhantomData,
+        }
+    }
+
+    // position the front at the first key within start and the back at the last key within end
+    pub(crate) fn range(root: &'a Node<V>, start: Bound<&[u8]>, end: Bound<&[u8]>) -> Self {
+        let mut iter = Iter::empty();
+        match start {
+            Bound::Included(key) => iter.front.seek(root, key, true),
+            Bound::Excluded(key) => iter.front.seek(root, key, false),
+            Bound::Unbounded => iter.front.first(root),
+        }
+        match end {
+            Bound::Included(key) => iter.back.seek_back(root, key, true),
+            Bound::Excluded(key) => iter.back.seek_back(root, key, false),
+            Bound::Unbounded => iter.back.last(root),
+        }
+
+        match (iter.front.leaf(), iter.back.leaf()) {
+            (Some(front), Some(back)) if front.key <= back.key => iter,
+            _ => Iter::empty(),
+        }
+    }
+
+    // both ends hand out the same leaf once they meet, after which the iterator is done
+    fn take_last(&mut self) -> bool {
+        let last = match (self.front.leaf(), self.back.leaf()) {
+            (Some(front), Some(back)) => ptr::eq(front, back),
+            _ => true,
+        };
+        if last {
+            self.front.clear();
+            self.back.clear();
+        }
+        last
+    }
+}
+
+impl<'a, K: ArtKey, V> Iter<'a, K, V> {
+    /// Decodes the keys handed out back into the key type of the tree.
+    ///
+    /// Panics on a key that doesn't decode, which only happens when the `ArtKey` impl of the key
+    /// type doesn't decode its own encodings.
+    pub fn decoded(self) -> Decoded<'a, K, V> {
+        Decoded { inner: self }
+    }
+}
+
+impl<'a, K, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a [u8], &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let leaf = self.front.leaf()?;
+        if !self.take_last() {
+            self.front.next();
+        }
+        Some((&leaf.key, &leaf.value))
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Iter<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let leaf = self.back.leaf()?;
+        if !self.take_last() {
+            self.back.prev();
+        }
+        Some((&leaf.key, &leaf.value))
+    }
+}
+
+impl<'a, K: ArtKey, V> Keys<'a, K, V> {
+    /// Decodes the keys handed out back into the key type of the tree.
+    pub fn decoded(self) -> DecodedKeys<Self, K> {
+        DecodedKeys::new(self)
+    }
+}
+
+impl<'a, K, V> Iterator for Keys<'a, K, V> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(key, _)| key)
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Keys<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(key, _)| key)
+    }
+}
+
+impl<'a, K, V> Iterator for Values<'a, K, V> {
+    type Item = &'a V;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|(_, value)| value)
+    }
+}
+
+impl<'a, K, V> DoubleEndedIterator for Values<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|(_, value)| value)
+    }
+}
+
+// panics as a key that doesn't decode means the `ArtKey` impl doesn't decode its own encodings
+fn decode<K: ArtKey>(key: &[u8]) -> K {
+    match K::decode(key) {
+        Some(decoded) => decoded,
+        None => panic!("key {:?} isn't an encoding of the key type", key),
+    }
+}
+
+impl<'a, K: ArtKey, V> Decoded<'a, K, V> {
+    fn decode((key, value): (&[u8], &'a V)) -> (K, &'a V) {
+        (decode(key), value)
+    }
+}
+
+impl<'a, K: ArtKey, V> Iterator for Decoded<'a, K, V> {
+    type Item = (K, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(Self::decode)
+    }
+}
+
+impl<'a, K: ArtKey, V> DoubleEndedIterator for Decoded<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(Self::decode)
+    }
+}
+
+impl<I, K> DecodedKeys<I, K> {
+    pub(crate) fn new(inner: I) -> Self {
+        DecodedKeys {
+            inner,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<'a, I: Iterator<Item = &'a [u8]>, K: ArtKey> Iterator for DecodedKeys<I, K> {
+    type Item = K;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(decode)
+    }
+}
+
+impl<'a, I: DoubleEndedIterator<Item = &'a [u8]>, K: ArtKey> DoubleEndedIterator
+    for DecodedKeys<I, K>
+{
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(decode)
+    }
+}
+
+impl<'a, 'k, V> Prefixes<'a, 'k, V> {
+    pub(crate) fn new(root: &'a Node<V>, key: Cow<'k, [u8]>) -> Self {
+        Prefixes {
+            node: Some(root),
+            depth: 0,
+            key,
+        }
+    }
+}
+
+// the stored keys that prefix the input all lie on the path the input takes down the tree,
+// either as the term leaf of a node on it or as the leaf it ends at. Only the bytes in partial
+// are compared on the way down, every candidate is checked against its whole key.
+impl<'a, 'k, V> Iterator for Prefixes<'a, 'k, V> {
+    type Item = (&'a [u8], &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let key: &[u8] = &self.key;
+            let leaf = match self.node.take()? {
+                Node::None => return None,
+                Node::Leaf(leaf) => leaf,
+                node => {
+                    let end = self.depth + node.prefix_len();
+                    if key.len() < end
+                        || node.prefix_match(key, self.depth)
+                            != min(MAX_PREFIX, node.partial().len())
+                    {
+                        return None;
+                    }
+                    self.node = key.get(end).and_then(|key_char| node.child_at(*key_char));
+                    self.depth = end + 1;
+                    match node.term_leaf() {
+                        Some(Node::Leaf(leaf)) => leaf,
+                        _ => continue,
+                    }
+                }
+            };
+            if key.starts_with(&leaf.key) {
+                return Some((&leaf.key, &leaf.value));
+            }
+        }
+    }
+}
+
+impl<'a, V> IterMut<'a, V> {
+    pub(crate) fn new(root: &'a mut Node<V>) -> Self {
+        let mut path = Path::new();
+        path.first(RawNode::new(root));
+        IterMut { path }
+    }
+}
+
+impl<'a, V> Iterator for IterMut<'a, V> {
+    type Item = (&'a [u8], &'a mut V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // the path moves on before the leaf is borrowed and never comes back to it, so the
+        // value stays the only way to the leaf
+        let leaf = self.path.leaf_ref()?;
+        self.path.next();
+        match unsafe { leaf.node_mut() } {
+            Node::Leaf(leaf) => Some((&leaf.key, &mut leaf.value)),
+            _ => None,
+        }
+    }
+}
+
+impl<K: ArtKey, V> IntoIter<K, V> {
+    pub(crate) fn new(root: Node<V>) -> Self {
+        IntoIter {
+            stack: vec![(root, 0)],
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Panics on a key that isn't an encoding of `K`, like `Iter::decoded`.
+impl<K: ArtKey, V> Iterator for IntoIter<K, V> {
+    type Item = (K, V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (node, from) = self.stack.last_mut()?;
+            let entry = match node.next_entry(*from) {
+                Some((position, _)) => {
+                    // the walk is past the entry once it is taken, so the gap it leaves behind
+                    // is never looked at
+                    *from = position + 1;
+                    node.entry_mut(position)
+                        .map(|entry| replace(entry, Node::None))
+                }
+                // a node without any entries left is done with, unless it is a leaf, which
+                // only the root can be here as the others are taken from their parent
+                None => match self.stack.pop() {
+                    Some((Node::Leaf(leaf), _)) => Some(Node::Leaf(leaf)),
+                    _ => None,
+                },
+            };
+            match entry {
+                Some(Node::Leaf(leaf)) => {
+                    let key = K::from_encoded(leaf.key)
+                        .unwrap_or_else(|| panic!("a key isn't an encoding of the key type"));
+                    return Some((key, leaf.value));
+                }
+                Some(Node::None) | None => {}
+                Some(inner) => self.stack.push((inner, 0)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Art;
+    use std::collections::BTreeMap;
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+    use std::ops::Bound;
+    use std::ops::Bound::{Excluded, Included, Unbounded};
+
+    fn _insert(art: &mut Art<Vec<u8>, Vec<u8>>, items: &[&str]) {
+        items.iter().for_each(|item| {
+            art.insert(Vec::from(item.as_bytes()), Vec::from(item.as_bytes()));
+        });
+    }
+
+    #[test]
+    fn test_iter_empty() {
+        let art: Art<Vec<u8>, Vec<u8>> = Art::new();
+        assert_eq!(art.iter().next(), None);
+
+        let mut art = Art::new();
+        _insert(&mut art, &["A"]);
+        let items: Vec<&[u8]> = art.keys().collect();
+        assert_eq!(items, vec!["A".as_bytes()]);
+    }
+
+    #[test]
+    fn test_iter_term_leaf_first() {
+        let mut art = Art::new();
+        let items = ["AMDs", "B", "A", "AMD", "AMDx", "Ab"];
+        _insert(&mut art, &items);
+
+        let keys: Vec<&[u8]> = art.keys().collect();
+        let expected: Vec<&[u8]> = ["A", "AMD", "AMDs", "AMDx", "Ab", "B"]
+            .iter()
+            .map(|k| k.as_bytes())
+            .collect();
+        assert_eq!(keys, expected);
+    }
+
+    #[test]
+    fn test_iter_node_sizes() {
+        // fill up a node of every size under a common prefix, with a term leaf on each
+        for count in [3usize, 10, 40, 256].iter() {
+            let mut art = Art::new();
+            let mut expected = BTreeMap::new();
+            for byte in (0..*count).rev() {
+                let key = vec![b'k', byte as u8];
+                art.insert(key.clone(), byte);
+                expected.insert(key, byte);
+            }
+            art.insert(vec![b'k'], 1000);
+            expected.insert(vec![b'k'], 1000);
+
+            let items: Vec<(&[u8], &usize)> = art.iter().collect();
+            let expected: Vec<(&[u8], &usize)> =
+                expected.iter().map(|(k, v)| (k.as_slice(), v)).collect();
+            assert_eq!(items, expected);
+        }
+    }
+
+    #[test]
+    fn test_iter_mut_and_into_iter_node_sizes() {
+        for count in [3usize, 10, 40, 256].iter() {
+            let mut art = Art::new();
+            let mut expected = BTreeMap::new();
+            for byte in (0..*count).rev() {
+                let key = vec![b'k', byte as u8];
+                art.insert(key.clone(), byte);
+                expected.insert(key, byte);
+            }
+            art.insert(vec![b'k'], 1000);
+            expected.insert(vec![b'k'], 1000);
+            art.insert(Vec::new(), 2000);
+            expected.insert(Vec::new(), 2000);
+
+            for (key, value) in art.iter_mut() {
+                *value += key.len();
+            }
+            for (key, value) in expected.iter_mut() {
+                *value += key.len();
+            }
+            assert!(art
+                .iter()
+                .eq(expected.iter().map(|(k, v)| (k.as_slice(), v))));
+            assert!(art.into_iter().eq(expected.into_iter()));
+        }
+
+        // a lone leaf at the root
+        let mut art = Art::new();
+        art.insert(b"a".to_vec(), 1);
+        for (_, value) in art.iter_mut() {
+            *value += 1;
+        }
+        assert_eq!(
+            art.into_iter().collect::<Vec<_>>(),
+            vec![(b"a".to_vec(), 2)]
+        );
+    }
+
+    #[test]
+    fn test_iter_words() {
+        let f_name = "/usr/share/dict/words";
+        let mut art = Art::new();
+        let mut expected = BTreeMap::new();
+        let reader = BufReader::new(File::open(f_name).unwrap());
+        for line in reader.lines().map_while(Result::ok) {
+            let key = line.trim().as_bytes().to_vec();
+            let len = key.len();
+            art.insert(key.clone(), len);
+            expected.insert(key, len);
+        }
+
+        assert!(art.keys().eq(expected.keys().map(|k| k.as_slice())));
+        assert!(art.values().eq(expected.values()));
+        assert_eq!(art.iter().count(), art.len());
+    }
+
+    #[test]
+    fn test_iter_rev_node_sizes() {
+        // the term leaf of every node comes out last when walking backwards
+        for count in [3usize, 10, 40, 256].iter() {
+            let mut art = Art::new();
+            let mut expected = BTreeMap::new();
+            for byte in 0..*count {
+                let key = vec![b'k', byte as u8];
+                art.insert(vec![b'k'], 1000);
+                art.insert(key.clone(), byte);
+                expected.insert(key, byte);
+            }
+            expected.insert(vec![b'k'], 1000);
+
+            let items: Vec<(&[u8], &usize)> = art.iter().rev().collect();
+            let expected: Vec<(&[u8], &usize)> = expected
+                .iter()
+                .rev()
+                .map(|(k, v)| (k.as_slice(), v))
+                .collect();
+            assert_eq!(art.iter().next_back(), Some(expected[0]));
+            assert_eq!(items, expected);
+        }
+    }
+
+    #[test]
+    fn test_iter_both_ends() {
+        let mut art = Art::new();
+        let items = ["A", "AMD", "AMDs", "AMDx", "Ab", "B"];
+        _insert(&mut art, &items);
+
+        let mut iter = art.keys();
+        assert_eq!(iter.next(), Some("A".as_bytes()));
+        assert_eq!(iter.next_back(), Some("B".as_bytes()));
+        assert_eq!(iter.next_back(), Some("Ab".as_bytes()));
+        assert_eq!(iter.next(), Some("AMD".as_bytes()));
+        assert_eq!(iter.next(), Some("AMDs".as_bytes()));
+        assert_eq!(iter.next_back(), Some("AMDx".as_bytes()));
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+
+        let mut iter = art.values();
+        iter.next();
+        assert!(iter.rev().eq(items[1..].iter().rev().map(|k| k.as_bytes())));
+    }
+
+    #[test]
+    fn test_iter_rev_words() {
+        let f_name = "/usr/share/dict/words";
+        let mut art = Art::new();
+        let mut expected = BTreeMap::new();
+        let reader = BufReader::new(File::open(f_name).unwrap());
+        for line in reader.lines().map_while(Result::ok) {
+            let key = line.trim().as_bytes().to_vec();
+            art.insert(key.clone(), ());
+            expected.insert(key, ());
+        }
+
+        assert!(art
+            .keys()
+            .rev()
+            .eq(expected.keys().rev().map(|k| k.as_slice())));
+
+        // alternate between the ends until they meet
+        let mut iter = art.keys();
+        let mut expected = expected.keys().map(|k| k.as_slice());
+        let mut step = 0;
+        loop {
+            let (item, matching) = if step % 3 == 0 {
+                (iter.next_back(), expected.next_back())
+            } else {
+                (iter.next(), expected.next())
+            };
+            assert_eq!(item, matching);
+            if item.is_none() {
+                break;
+            }
+            step += 1;
+        }
+    }
+
+    #[test]
+    fn test_prefix_iter() {
+        let mut art = Art::new();
+        let items = [
+            "Con",
+            "Cone",
+            "Cong",
+            "Congo",
+            "Congregationalist",
+            "Congregationalist's",
+            "Congregationalists",
+            "Dong",
+        ];
+        _insert(&mut art, &items);
+
+        let scan = |prefix: &str| -> Vec<String> {
+            art.prefix_iter(prefix.as_bytes())
+                .map(|(k, _)| String::from_utf8(k.to_vec()).unwrap())
+                .collect()
+        };
+        assert_eq!(scan("").len(), items.len());
+        assert_eq!(
+            scan("Cong"),
+            vec![
+                "Cong",
+                "Congo",
+                "Congregationalist",
+                "Congregationalist's",
+                "Congregationalists",
+            ]
+        );
+        // ends in the middle of a compressed path longer than MAX_PREFIX
+        assert_eq!(
+            scan("Congregation"),
+            vec![
+                "Congregationalist",
+                "Congregationalist's",
+                "Congregationalists",
+            ]
+        );
+        assert_eq!(scan("Congregationalist'"), vec!["Congregationalist's"]);
+        assert_eq!(scan("Congregationalisx"), Vec::<String>::new());
+        assert_eq!(scan("Congregationalists"), vec!["Congregationalists"]);
+        assert_eq!(scan("Congregationalistss"), Vec::<String>::new());
+        assert_eq!(scan("Cone"), vec!["Cone"]);
+        assert_eq!(scan("E"), Vec::<String>::new());
+        assert_eq!(scan("Do"), vec!["Dong"]);
+    }
+
+    #[test]
+    fn test_prefix_iter_words() {
+        let f_name = "/usr/share/dict/words";
+        let mut art = Art::new();
+        let mut expected = BTreeMap::new();
+        let reader = BufReader::new(File::open(f_name).unwrap());
+        for line in reader.lines().map_while(Result::ok) {
+            let key = line.trim().as_bytes().to_vec();
+            art.insert(key.clone(), ());
+            expected.insert(key, ());
+        }
+
+        // every prefix of a sample of the words
+        for word in expected.keys().step_by(997) {
+            for end in 0..=word.len() {
+                let prefix = &word[..end];
+                let scanned = art.prefix_iter(prefix).map(|(k, _)| k);
+                let matching = expected
+                    .range(prefix.to_vec()..)
+                    .take_while(|(k, _)| k.starts_with(prefix))
+                    .map(|(k, _)| k.as_slice());
+                assert!(scanned.eq(matching), "prefix {:?}", prefix);
+            }
+        }
+    }
+
+    fn _assert_range(
+        art: &Art<Vec<u8>, ()>,
+        expected: &BTreeMap<Vec<u8>, ()>,
+        start: Bound<&[u8]>,
+        end: Bound<&[u8]>,
+    ) {
+        // BTreeMap panics on inverted ranges, those should just come back empty here
+        let inverted = match (start, end) {
+            (Included(s), Included(e)) => s > e,
+            (Included(s), Excluded(e)) | (Excluded(s), Included(e)) => s > e,
+            (Excluded(s), Excluded(e)) => s >= e,
+            _ => false,
+        };
+        let scanned: Vec<&[u8]> = art.range::<[u8], _>((start, end)).map(|(k, _)| k).collect();
+        if inverted {
+            assert!(scanned.is_empty(), "{:?}..{:?}", start, end);
+            assert_eq!(art.range::<[u8], _>((start, end)).next_back(), None);
+            return;
+        }
+        let matching: Vec<&[u8]> = expected
+            .range::<[u8], _>((start, end))
+            .map(|(k, _)| k.as_slice())
+            .collect();
+        assert_eq!(scanned, matching, "{:?}..{:?}", start, end);
+
+        let reversed: Vec<&[u8]> = art
+            .range::<[u8], _>((start, end))
+            .rev()
+            .map(|(k, _)| k)
+            .collect();
+        let matching: Vec<&[u8]> = matching.into_iter().rev().collect();
+        assert_eq!(reversed, matching, "rev {:?}..{:?}", start, end);
+    }
+
+    #[test]
+    fn test_range() {
+        let mut art = Art::new();
+        let mut expected = BTreeMap::new();
+        let items = [
+            "A",
+            "AMD",
+            "AMDs",
+            "Ab",
+            "Congregationalist",
+            "Congregationalist's",
+            "Congregationalists",
+            "Dong",
+        ];
+        for item in items.iter() {
+            art.insert(item.as_bytes().to_vec(), ());
+            expected.insert(item.as_bytes().to_vec(), ());
+        }
+
+        let bounds = [
+            "",
+            "A",
+            "AM",
+            "AMD",
+            "AMDa",
+            "AMDz",
+            "B",
+            "Congregation",
+            "Congregationalist",
+            "Congregationalista",
+            "Congregationalisz",
+            "Congregationalists",
+            "Z",
+        ];
+        for start in bounds.iter() {
+            for end in bounds.iter() {
+                let (s, e) = (start.as_bytes(), end.as_bytes());
+                for (start, end) in [
+                    (Included(s), Included(e)),
+                    (Included(s), Excluded(e)),
+                    (Excluded(s), Included(e)),
+                    (Excluded(s), Excluded(e)),
+                    (Unbounded, Excluded(e)),
+                    (Excluded(s), Unbounded),
+                ]
+                .iter()
+                {
+                    _assert_range(&art, &expected, *start, *end);
+                }
+            }
+        }
+        assert_eq!(art.range::<[u8], _>(..).count(), items.len());
+    }
+
+    #[test]
+    fn test_range_node_sizes() {
+        for count in [3usize, 10, 40, 256].iter() {
+            let mut art = Art::new();
+            let mut expected = BTreeMap::new();
+            // spread the keys out so that seeks land on missing bytes as well
+            for byte in (0..*count).map(|b| (b * 255 / count) as u8) {
+                art.insert(vec![b'k', byte], ());
+                expected.insert(vec![b'k', byte], ());
+            }
+            art.insert(vec![b'k'], ());
+            expected.insert(vec![b'k'], ());
+
+            for byte in 0..=255u8 {
+                let bound = vec![b'k', byte];
+                _assert_range(&art, &expected, Included(&bound), Unbounded);
+                _assert_range(&art, &expected, Excluded(&bound), Unbounded);
+                _assert_range(&art, &expected, Excluded(b"k"), Included(&bound));
+                _assert_range(&art, &expected, Unbounded, Excluded(&bound));
+            }
+        }
+    }
+
+    #[test]
+    fn test_range_words() {
+        let f_name = "/usr/share/dict/words";
+        let mut art = Art::new();
+        let mut expected = BTreeMap::new();
+        let reader = BufReader::new(File::open(f_name).unwrap());
+        for line in reader.lines().map_while(Result::ok) {
+            let key = line.trim().as_bytes().to_vec();
+            art.insert(key.clone(), ());
+            expected.insert(key, ());
+        }
+
+        let words: Vec<&Vec<u8>> = expected.keys().step_by(1009).collect();
+        for pair in words.windows(2) {
+            let (start, end) = (pair[0].as_slice(), pair[1].as_slice());
+            let shorter = &start[..start.len() / 2];
+            _assert_range(&art, &expected, Included(start), Excluded(end));
+            _assert_range(&art, &expected, Excluded(start), Included(end));
+            _assert_range(&art, &expected, Included(shorter), Included(end));
+            _assert_range(&art, &expected, Excluded(shorter), Excluded(start));
+        }
+    }
+}
@@ -0,0 +1,684 @@
+use crate::{ArtKey, KeyLookup, KeyPrefix};
+use std::borrow::Cow;
+use std::convert::TryInto;
+use std::mem::size_of;
+
+// splits len bytes off the front of bytes
+fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Option<&'a [u8]> {
+    if bytes.len() < len {
+        return None;
+    }
+    let (head, rest) = bytes.split_at(len);
+    *bytes = rest;
+    Some(head)
+}
+
+// a zero byte inside a nested byte string is written as 0x00 0xff, and the string ends with
+// 0x00 0x00. The end sorts before any byte that could follow, so a string sorts before every
+// longer string it is a prefix of, whatever the next element of the tuple starts with. Neither
+// is a prefix of the other either, so the encoding of a string followed by anything else never
+// starts with the encoding of a shorter string.
+pub(crate) fn encode_escaped(bytes: &[u8], buf: &mut Vec<u8>) {
+    for byte in bytes {
+        buf.push(*byte);
+        if *byte == 0 {
+            buf.push(0xff);
+        }
+    }
+    buf.extend_from_slice(&[0, 0]);
+}
+
+pub(crate) fn decode_escaped(bytes: &mut &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    let mut idx = 0;
+    loop {
+        match *bytes.get(idx)? {
+            0 => match *bytes.get(idx + 1)? {
+                0 => {
+                    *bytes = &bytes[idx + 2..];
+                    return Some(decoded);
+                }
+                0xff => {
+                    decoded.push(0);
+                    idx += 2;
+                }
+                _ => return None,
+            },
+            byte => {
+                decoded.push(byte);
+                idx += 1;
+            }
+        }
+    }
+}
+
+macro_rules! unsigned_key {
+    ($($ty:ty),*) => {
+        $(
+            impl ArtKey for $ty {
+                fn encode_into(&self, buf: &mut Vec<u8>) {
+                    buf.extend_from_slice(&self.to_be_bytes());
+                }
+
+                fn decode_nested(bytes: &mut &[u8]) -> Option<Self> {
+                    let head = take(bytes, size_of::<$ty>())?;
+                    Some(<$ty>::from_be_bytes(head.try_into().ok()?))
+                }
+            }
+        )*
+    };
+}
+
+// flipping the sign bit moves the negative numbers below the positive ones, after that the
+// two's complement bits sort like unsigned ones
+macro_rules! signed_key {
+    ($($ty:ty => $unsigned:ty),*) => {
+        $(
+            impl ArtKey for $ty {
+                fn encode_into(&self, buf: &mut Vec<u8>) {
+                    let flipped = (*self as $unsigned) ^ (1 << (<$unsigned>::BITS - 1));
+                    flipped.encode_into(buf);
+                }
+
+                fn decode_nested(bytes: &mut &[u8]) -> Option<Self> {
+                    let flipped = <$unsigned>::decode_nested(bytes)?;
+                    Some((flipped ^ (1 << (<$unsigned>::BITS - 1))) as $ty)
+                }
+            }
+        )*
+    };
+}
+
+unsigned_key!(u8, u16, u32, u64, u128);
+signed_key!(i8 => u8, i16 => u16, i32 => u32, i64 => u64, i128 => u128);
+
+// the IEEE 754 total order: a positive float only needs its sign bit set to sort above the
+// negative ones, a negative float has all of its bits flipped so that larger magnitudes sort
+// lower. That puts -NaN < -inf < ... < -0.0 < +0.0 < ... < +inf < +NaN, as total_cmp does.
+macro_rules! float_key {
+    ($($ty:ty => $bits:ty),*) => {
+        $(
+            impl ArtKey for $ty {
+                fn encode_into(&self, buf: &mut Vec<u8>) {
+                    let bits = self.to_bits();
+                    let sign = 1 << (<$bits>::BITS - 1);
+                    let ordered = if bits & sign == 0 { bits | sign } else { !bits };
+                    ordered.encode_into(buf);
+                }
+
+                fn decode_nested(bytes: &mut &[u8]) -> Option<Self> {
+                    let ordered = <$bits>::decode_nested(bytes)?;
+                    let sign = 1 << (<$bits>::BITS - 1);
+                    let bits = if ordered & sign == 0 { !ordered } else { ordered & !sign };
+                    Some(<$ty>::from_bits(bits))
+                }
+            }
+        )*
+    };
+}
+
+float_key!(f32 => u32, f64 => u64);
+
+// usize and isize are stored as 64 bits, so the encoding doesn't depend on the platform
+impl ArtKey for usize {
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        (*self as u64).encode_into(buf);
+    }
+
+    fn decode_nested(bytes: &mut &[u8]) -> Option<Self> {
+        u64::decode_nested(bytes)?.try_into().ok()
+    }
+}
+
+impl ArtKey for isize {
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        (*self as i64).encode_into(buf);
+    }
+
+    fn decode_nested(bytes: &mut &[u8]) -> Option<Self> {
+        i64::decode_nested(bytes)?.try_into().ok()
+    }
+}
+
+impl ArtKey for [u8] {
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self);
+    }
+
+    fn encode(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self)
+    }
+
+    fn encode_nested(&self, buf: &mut Vec<u8>) {
+        encode_escaped(self, buf);
+    }
+}
+
+impl ArtKey for Vec<u8> {
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self);
+    }
+
+    fn encode(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self)
+    }
+
+    fn into_encoded(self) -> Vec<u8> {
+        self
+    }
+
+    fn encode_nested(&self, buf: &mut Vec<u8>) {
+        encode_escaped(self, buf);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        Some(bytes.to_vec())
+    }
+
+    fn from_encoded(bytes: Vec<u8>) -> Option<Self> {
+        Some(bytes)
+    }
+
+    fn decode_nested(bytes: &mut &[u8]) -> Option<Self> {
+        decode_escaped(bytes)
+    }
+}
+
+impl ArtKey for Box<[u8]> {
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self);
+    }
+
+    fn encode(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self)
+    }
+
+    fn into_encoded(self) -> Vec<u8> {
+        self.into_vec()
+    }
+
+    fn encode_nested(&self, buf: &mut Vec<u8>) {
+        encode_escaped(self, buf);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        Some(bytes.into())
+    }
+
+    fn from_encoded(bytes: Vec<u8>) -> Option<Self> {
+        Some(bytes.into_boxed_slice())
+    }
+
+    fn decode_nested(bytes: &mut &[u8]) -> Option<Self> {
+        decode_escaped(bytes).map(Vec::into_boxed_slice)
+    }
+}
+
+impl<'a> ArtKey for Cow<'a, [u8]> {
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self);
+    }
+
+    fn encode(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self)
+    }
+
+    fn into_encoded(self) -> Vec<u8> {
+        self.into_owned()
+    }
+
+    fn encode_nested(&self, buf: &mut Vec<u8>) {
+        encode_escaped(self, buf);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        Some(Cow::Owned(bytes.to_vec()))
+    }
+
+    fn from_encoded(bytes: Vec<u8>) -> Option<Self> {
+        Some(Cow::Owned(bytes))
+    }
+
+    fn decode_nested(bytes: &mut &[u8]) -> Option<Self> {
+        decode_escaped(bytes).map(Cow::Owned)
+    }
+}
+
+impl<const N: usize> ArtKey for [u8; N] {
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self);
+    }
+
+    fn encode(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self)
+    }
+
+    // all the arrays of a type have the same length, so they don't need a terminator
+    fn decode_nested(bytes: &mut &[u8]) -> Option<Self> {
+        take(bytes, N)?.try_into().ok()
+    }
+}
+
+impl ArtKey for str {
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self.as_bytes());
+    }
+
+    fn encode(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self.as_bytes())
+    }
+
+    fn encode_nested(&self, buf: &mut Vec<u8>) {
+        encode_escaped(self.as_bytes(), buf);
+    }
+}
+
+impl ArtKey for String {
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self.as_bytes());
+    }
+
+    fn encode(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self.as_bytes())
+    }
+
+    fn into_encoded(self) -> Vec<u8> {
+        self.into_bytes()
+    }
+
+    fn encode_nested(&self, buf: &mut Vec<u8>) {
+        encode_escaped(self.as_bytes(), buf);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+
+    fn from_encoded(bytes: Vec<u8>) -> Option<Self> {
+        String::from_utf8(bytes).ok()
+    }
+
+    fn decode_nested(bytes: &mut &[u8]) -> Option<Self> {
+        String::from_utf8(decode_escaped(bytes)?).ok()
+    }
+}
+
+impl ArtKey for Box<str> {
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self.as_bytes());
+    }
+
+    fn encode(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self.as_bytes())
+    }
+
+    fn into_encoded(self) -> Vec<u8> {
+        self.into_boxed_bytes().into_vec()
+    }
+
+    fn encode_nested(&self, buf: &mut Vec<u8>) {
+        encode_escaped(self.as_bytes(), buf);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        String::decode(bytes).map(String::into_boxed_str)
+    }
+
+    fn from_encoded(bytes: Vec<u8>) -> Option<Self> {
+        String::from_encoded(bytes).map(String::into_boxed_str)
+    }
+
+    fn decode_nested(bytes: &mut &[u8]) -> Option<Self> {
+        String::decode_nested(bytes).map(String::into_boxed_str)
+    }
+}
+
+impl<'a> ArtKey for Cow<'a, str> {
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(self.as_bytes());
+    }
+
+    fn encode(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self.as_bytes())
+    }
+
+    fn into_encoded(self) -> Vec<u8> {
+        self.into_owned().into_bytes()
+    }
+
+    fn encode_nested(&self, buf: &mut Vec<u8>) {
+        encode_escaped(self.as_bytes(), buf);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        String::decode(bytes).map(Cow::Owned)
+    }
+
+    fn from_encoded(bytes: Vec<u8>) -> Option<Self> {
+        String::from_encoded(bytes).map(Cow::Owned)
+    }
+
+    fn decode_nested(bytes: &mut &[u8]) -> Option<Self> {
+        String::decode_nested(bytes).map(Cow::Owned)
+    }
+}
+
+// every element of a tuple is nested, the last one included, so that a tuple is a prefix of
+// the longer tuples starting with it and prefix scans over the leading elements work
+macro_rules! tuple_key {
+    ($($name:ident)+) => {
+        impl<$($name: ArtKey),+> ArtKey for ($($name,)+) {
+            #[allow(non_snake_case)]
+            fn encode_into(&self, buf: &mut Vec<u8>) {
+                let ($($name,)+) = self;
+                $($name.encode_nested(buf);)+
+            }
+
+            fn decode_nested(bytes: &mut &[u8]) -> Option<Self> {
+                Some(($($name::decode_nested(bytes)?,)+))
+            }
+        }
+    };
+}
+
+tuple_key!(A);
+tuple_key!(A B);
+tuple_key!(A B C);
+tuple_key!(A B C D);
+tuple_key!(A B C D E);
+tuple_key!(A B C D E F);
+
+impl<K: ArtKey> KeyLookup<K> for K {}
+
+impl<K, Q: KeyLookup<K> + ?Sized> KeyPrefix<K> for Q {}
+
+// the strings and byte strings all encode to their bytes, so each of them finds the others
+macro_rules! bytes_lookup {
+    ($key:ty => $($query:ty),*) => {
+        impl KeyLookup<$key> for [u8] {}
+        impl KeyLookup<$key> for str {}
+        impl<const N: usize> KeyLookup<$key> for [u8; N] {}
+        impl<const N: usize> KeyLookup<[u8; N]> for $key {}
+        $(impl KeyLookup<$key> for $query {})*
+    };
+}
+
+bytes_lookup!(Vec<u8> => Box<[u8]>, Cow<'_, [u8]>, String, Box<str>, Cow<'_, str>);
+bytes_lookup!(Box<[u8]> => Vec<u8>, Cow<'_, [u8]>, String, Box<str>, Cow<'_, str>);
+bytes_lookup!(Cow<'_, [u8]> => Vec<u8>, Box<[u8]>, String, Box<str>, Cow<'_, str>);
+bytes_lookup!(String => Vec<u8>, Box<[u8]>, Cow<'_, [u8]>, Box<str>, Cow<'_, str>);
+bytes_lookup!(Box<str> => Vec<u8>, Box<[u8]>, Cow<'_, [u8]>, String, Cow<'_, str>);
+bytes_lookup!(Cow<'_, str> => Vec<u8>, Box<[u8]>, Cow<'_, [u8]>, String, Box<str>);
+
+impl<const N: usize> KeyLookup<[u8; N]> for [u8] {}
+impl<const N: usize> KeyLookup<[u8; N]> for str {}
+
+// a tuple of the leading elements encodes to a prefix of the tuples starting with them
+macro_rules! tuple_prefix {
+    ($($prefix:ident)+ => $($rest:ident)+) => {
+        impl<$($prefix: ArtKey,)+ $($rest: ArtKey),+> KeyPrefix<($($prefix,)+ $($rest,)+)>
+            for ($($prefix,)+)
+        {
+        }
+    };
+}
+
+tuple_prefix!(A => B);
+tuple_prefix!(A => B C);
+tuple_prefix!(A B => C);
+tuple_prefix!(A => B C D);
+tuple_prefix!(A B => C D);
+tuple_prefix!(A B C => D);
+tuple_prefix!(A => B C D E);
+tuple_prefix!(A B => C D E);
+tuple_prefix!(A B C => D E);
+tuple_prefix!(A B C D => E);
+tuple_prefix!(A => B C D E F);
+tuple_prefix!(A B => C D E F);
+tuple_prefix!(A B C => D E F);
+tuple_prefix!(A B C D => E F);
+tuple_prefix!(A B C D E => F);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Art;
+    use std::fmt::Debug;
+
+    fn _assert_order<K: ArtKey + Ord + Debug + Clone>(keys: &[K]) {
+        let mut by_value = keys.to_vec();
+        by_value.sort();
+        let mut by_encoding = keys.to_vec();
+        by_encoding.sort_by(|a, b| a.encode().cmp(&b.encode()));
+        assert_eq!(by_value, by_encoding);
+        for key in keys {
+            assert_eq!(K::decode(&key.encode()).as_ref(), Some(key));
+            let mut nested = Vec::new();
+            key.encode_nested(&mut nested);
+            let mut rest = nested.as_slice();
+            assert_eq!(K::decode_nested(&mut rest).as_ref(), Some(key));
+            assert!(rest.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_integer_order() {
+        _assert_order(&[0u8, 1, 127, 128, 255]);
+        _assert_order(&[0u32, 1, 255, 256, 65535, 65536, u32::MAX]);
+        _assert_order(&[0u128, 1, u64::MAX as u128, u128::MAX]);
+        _assert_order(&[i8::MIN, -1, 0, 1, i8::MAX]);
+        _assert_order(&[i32::MIN, -65536, -256, -1, 0, 1, 255, 256, i32::MAX]);
+        _assert_order(&[i64::MIN, -1, 0, 1, i64::MAX]);
+        _assert_order(&[i128::MIN, -1, 0, 1, i128::MAX]);
+        _assert_order(&[0usize, 1, usize::MAX]);
+        _assert_order(&[isize::MIN, -1, 0, 1, isize::MAX]);
+        assert_eq!(258u16.encode().as_ref(), &[1, 2]);
+        assert_eq!((-1i16).encode().as_ref(), &[0x7f, 0xff]);
+        assert_eq!(u16::decode(&[1]), None);
+        assert_eq!(u16::decode(&[1, 2, 3]), None);
+    }
+
+    #[test]
+    fn test_string_order() {
+        let words: Vec<String> = ["", "a", "a\0", "a\0b", "ab", "b", "\u{e9}"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        _assert_order(&words);
+        let bytes: Vec<Vec<u8>> = words.iter().map(|s| s.as_bytes().to_vec()).collect();
+        _assert_order(&bytes);
+        let boxed: Vec<Box<[u8]>> = bytes.iter().map(|b| b.clone().into_boxed_slice()).collect();
+        _assert_order(&boxed);
+        let cows: Vec<Cow<'_, str>> = words.iter().map(|s| Cow::Borrowed(s.as_str())).collect();
+        _assert_order(&cows);
+        _assert_order(&[[0u8, 0], [0, 1], [1, 0], [255, 255]]);
+        assert!(matches!("ab".encode(), Cow::Borrowed(_)));
+        assert_eq!(String::decode(&[0xff]), None);
+        assert_eq!(Vec::<u8>::decode_nested(&mut &[b'a', 0, 1][..]), None);
+    }
+
+    #[test]
+    fn test_tuple_order() {
+        let mut keys = Vec::new();
+        for first in ["", "a", "a\0", "ab"].iter() {
+            for second in [0u8, 1, 255].iter() {
+                for third in [-1i32, 0, 1].iter() {
+                    keys.push((first.to_string(), *second, *third));
+                }
+            }
+        }
+        _assert_order(&keys);
+        _assert_order(&[("a".to_string(),), ("a\0".to_string(),), ("b".to_string(),)]);
+        _assert_order(&[
+            (1u8, "x".to_string(), -1i8, 2u16, vec![0u8], [1u8; 2]),
+            (1u8, "x".to_string(), -1i8, 2u16, vec![0u8, 0], [0u8; 2]),
+        ]);
+    }
+
+    #[test]
+    fn test_art_with_integer_keys() {
+        let mut art = Art::new();
+        let keys = [5i64, -3, 0, i64::MIN, 1 << 40, -(1 << 40), i64::MAX, -1];
+        for key in keys.iter() {
+            art.insert(*key, key.wrapping_mul(2));
+        }
+        let mut sorted = keys.to_vec();
+        sorted.sort();
+        let scanned: Vec<i64> = art.keys().map(|k| i64::decode(k).unwrap()).collect();
+        assert_eq!(scanned, sorted);
+        assert_eq!(art.search(&-3i64), Some(&-6));
+        assert_eq!(art.remove(&(1i64 << 40)), Some(1 << 41));
+        assert_eq!(art.search(&(1i64 << 40)), None);
+        let negative: Vec<i64> = art
+            .range(i64::MIN..0)
+            .map(|(k, _)| i64::decode(k).unwrap())
+            .collect();
+        assert_eq!(negative, vec![i64::MIN, -(1 << 40), -3, -1]);
+    }
+
+    #[test]
+    fn test_lookups_take_the_key_type() {
+        // the literals are inferred as the key type rather than falling back to i32
+        let mut art: Art<u64, u64> = (0..10).map(|key| (key, key * 10)).collect();
+        assert_eq!(art.search(&5), Some(&50));
+        *art.get_mut(&3).unwrap() += 1;
+        assert_eq!(art[&3], 31);
+        assert!(art.contains_key(&9));
+        assert_eq!(art.remove(&7), Some(70));
+        assert_eq!(art.range(2..5).count(), 3);
+        let mut cursor = art.cursor();
+        cursor.seek(&7);
+        assert_eq!(cursor.key().map(u64::decode), Some(Some(8)));
+
+        let signed: Art<i64, ()> = (-5..5).map(|key| (key, ())).collect();
+        assert_eq!(signed.range(-2..2).count(), 4);
+
+        // the string and byte string forms all find each other
+        let mut strings: Art<String, usize> = Art::new();
+        strings.insert("ab".to_string(), 1);
+        assert_eq!(strings.search("ab"), Some(&1));
+        assert_eq!(strings.search(&b"ab"[..]), Some(&1));
+        assert_eq!(strings.search(b"ab"), Some(&1));
+        assert_eq!(strings.search(&b"ab".to_vec()), Some(&1));
+        assert_eq!(strings.search(&Cow::Borrowed("ab")), Some(&1));
+        let mut cursor = strings.cursor_mut();
+        cursor.insert_after("b".to_string(), 2);
+        cursor.seek("b");
+        assert_eq!(cursor.value(), Some(&2));
+        let arrays: Art<[u8; 2], ()> = vec![(*b"ab", ())].into_iter().collect();
+        assert!(arrays.contains_key("ab") && arrays.contains_key(&b"ab".to_vec()));
+    }
+
+    fn _floats() -> Vec<f64> {
+        let mut floats = vec![
+            f64::NAN,
+            -f64::NAN,
+            f64::from_bits(f64::NAN.to_bits() | 1),
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+            f64::MAX,
+            f64::MIN,
+            f64::MIN_POSITIVE,
+            -f64::MIN_POSITIVE,
+            f64::from_bits(1),
+            -f64::from_bits(1),
+            0.0,
+            -0.0,
+            1.0,
+            -1.0,
+            0.1,
+            -0.1,
+            1e300,
+            -1e-300,
+        ];
+        for i in (-50..50).filter(|i| *i != 0) {
+            floats.push(i as f64 * 0.37);
+        }
+        floats
+    }
+
+    #[test]
+    fn test_float_order() {
+        let mut by_value = _floats();
+        by_value.sort_by(|a, b| a.total_cmp(b));
+        let mut by_encoding = _floats();
+        by_encoding.sort_by(|a, b| a.encode().cmp(&b.encode()));
+        let bits = |floats: &[f64]| floats.iter().map(|f| f.to_bits()).collect::<Vec<u64>>();
+        assert_eq!(bits(&by_value), bits(&by_encoding));
+        for float in _floats() {
+            let decoded = f64::decode(&float.encode()).unwrap();
+            assert_eq!(decoded.to_bits(), float.to_bits());
+        }
+
+        let mut by_value: Vec<f32> = _floats().iter().map(|f| *f as f32).collect();
+        by_value.sort_by(|a, b| a.total_cmp(b));
+        let mut by_encoding = by_value.clone();
+        by_encoding.sort_by(|a, b| a.encode().cmp(&b.encode()));
+        for (a, b) in by_value.iter().zip(by_encoding.iter()) {
+            assert_eq!(a.to_bits(), b.to_bits());
+            assert_eq!(f32::decode(&a.encode()).unwrap().to_bits(), a.to_bits());
+        }
+        assert!((-0.0f64).encode() < 0.0f64.encode());
+        assert_eq!(f32::decode(&[0; 3]), None);
+    }
+
+    #[test]
+    fn test_art_with_float_keys() {
+        let mut art = Art::new();
+        for (idx, float) in _floats().into_iter().enumerate() {
+            art.insert(float, idx);
+        }
+        let mut sorted = _floats();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+        let scanned: Vec<u64> = art.iter().decoded().map(|(k, _)| k.to_bits()).collect();
+        let expected: Vec<u64> = sorted.iter().map(|f| f.to_bits()).collect();
+        assert_eq!(scanned, expected);
+        let reversed: Vec<u64> = art
+            .iter()
+            .decoded()
+            .rev()
+            .map(|(k, _)| k.to_bits())
+            .collect();
+        assert!(reversed.into_iter().eq(expected.into_iter().rev()));
+
+        let readings: Vec<f64> = art
+            .range(-1.0..1.0)
+            .decoded()
+            .map(|(k, _): (f64, _)| k)
+            .collect();
+        let matching: Vec<f64> = sorted
+            .iter()
+            .copied()
+            .filter(|f| (-1.0..1.0).contains(f))
+            .collect();
+        assert_eq!(readings, matching);
+        assert!(readings.contains(&-0.0) && readings.contains(&0.0));
+        assert_eq!(art.search(&f64::NEG_INFINITY), Some(&4));
+        assert_eq!(art.range(f64::INFINITY..).count(), 3);
+    }
+
+    #[test]
+    fn test_art_with_tuple_keys() {
+        let mut art = Art::new();
+        for tenant in ["acme", "acme\0", "acmex"].iter() {
+            for table in ["t", "tt"].iter() {
+                for row in 0u32..3 {
+                    art.insert((tenant.to_string(), table.to_string(), row), row);
+                }
+            }
+        }
+        assert_eq!(art.len(), 18);
+        let acme: Vec<(String, String, u32)> = art
+            .prefix_iter(&("acme".to_string(),))
+            .map(|(k, _)| ArtKey::decode(k).unwrap())
+            .collect();
+        assert_eq!(acme.len(), 6);
+        assert!(acme.iter().all(|(tenant, _, _)| tenant == "acme"));
+        let table = ("acme".to_string(), "t".to_string());
+        assert_eq!(art.prefix_iter(&table).count(), 3);
+        assert_eq!(
+            art.search(&("acmex".to_string(), "tt".to_string(), 2u32)),
+            Some(&2)
+        );
+    }
+}
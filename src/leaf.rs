@@ -1,9 +1,8 @@
 use crate::Leaf;
-use std::borrow::Borrow;
-use std::fmt::{Display, Error, Formatter};
+use std::fmt::{Debug, Display, Error, Formatter};
 
-impl Leaf {
-    pub(crate) fn new(new_key: Vec<u8>, new_value: Vec<u8>) -> Self {
+impl<V> Leaf<V> {
+    pub(crate) fn new(new_key: Vec<u8>, new_value: V) -> Self {
         Leaf {
             key: new_key,
             value: new_value,
@@ -11,14 +10,9 @@ impl Leaf {
     }
 }
 
-impl Display for Leaf {
+impl<V: Debug> Display for Leaf<V> {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
-        let as_char_vec = |vec: &Vec<u8>| -> Vec<char> { vec.iter().map(|i| *i as char).collect() };
-        write!(
-            f,
-            "Leaf: {:?}={:?}",
-            as_char_vec(self.key.borrow()),
-            as_char_vec(self.value.borrow())
-        )
+        let as_char_vec = |vec: &[u8]| -> Vec<char> { vec.iter().map(|i| *i as char).collect() };
+        write!(f, "Leaf: {:?}={:?}", as_char_vec(&self.key), self.value)
     }
 }
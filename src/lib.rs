@@ -1,20 +1,258 @@
+use std::borrow::Cow;
+use std::marker::PhantomData;
+use std::net::IpAddr;
+use std::ptr::NonNull;
+
 const MAX_PREFIX: usize = 8;
 
-#[derive(Debug)]
-pub struct Art {
-    root: Node,
+pub struct Art<K, V> {
+    root: Node<V>,
     size: usize,
+    marker: PhantomData<K>,
+}
+
+/// A set of keys, stored in the nodes of an `Art` with a zero-sized value so that the leaves
+/// hold nothing but the key.
+pub struct ArtSet<K> {
+    map: Art<K, ()>,
+}
+
+/// Errors reported by the fallible operations of an `Art` in place of panicking.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ArtError {
+    /// The tree is in a state its invariants rule out, e.g. a node copied into a node type it
+    /// can't grow or shrink into, or an inner node without any leaves below it.
+    Corrupted(&'static str),
+    /// Allocating a node or room for another child failed.
+    AllocationFailed,
+    /// A key handed to `CursorMut::try_insert_after` doesn't sort after the current key.
+    KeyOutOfOrder,
+}
+
+/// Error returned by `Regex::new` for a pattern it can't compile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RegexError {
+    // the byte offset in the pattern the error was found at
+    position: usize,
+    reason: &'static str,
+}
+
+/// A key that can be stored in an `Art`.
+///
+/// The tree only ever sees the bytes a key encodes to, so the encoding has to sort the same
+/// way the keys do: unsigned integers are stored big-endian, signed integers with their sign
+/// bit flipped, floats in the order of `total_cmp`, and strings and byte strings as their
+/// bytes. Lookups take the key type itself or one of the forms `KeyLookup` admits for it.
+pub trait ArtKey {
+    /// Appends the encoding of the key to `buf`.
+    fn encode_into(&self, buf: &mut Vec<u8>);
+
+    /// Returns the encoding of the key, borrowing it where the key already is its encoding.
+    fn encode(&self) -> Cow<'_, [u8]> {
+        let mut buf = Vec::new();
+        self.encode_into(&mut buf);
+        Cow::Owned(buf)
+    }
+
+    /// Turns the key into its encoding, reusing its buffer where it can.
+    fn into_encoded(self) -> Vec<u8>
+    where
+        Self: Sized,
+    {
+        self.encode().into_owned()
+    }
+
+    /// Appends an encoding of the key that keeps sorting correctly with more bytes after it,
+    /// as the elements of a tuple need. Only variable-length keys have to escape and terminate
+    /// their bytes for that.
+    fn encode_nested(&self, buf: &mut Vec<u8>) {
+        self.encode_into(buf)
+    }
+
+    /// Decodes a key from the whole of `bytes`, or returns `None` if they aren't an encoding
+    /// of this type.
+    fn decode(bytes: &[u8]) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        let mut rest = bytes;
+        let key = Self::decode_nested(&mut rest)?;
+        if rest.is_empty() {
+            Some(key)
+        } else {
+            None
+        }
+    }
+
+    /// Decodes a key from its encoding, reusing the buffer where the key is stored as its
+    /// bytes.
+    fn from_encoded(bytes: Vec<u8>) -> Option<Self>
+    where
+        Self: Sized,
+    {
+        Self::decode(&bytes)
+    }
+
+    /// Decodes a key written by `encode_nested` off the front of `bytes`, advancing them past
+    /// it.
+    fn decode_nested(bytes: &mut &[u8]) -> Option<Self>
+    where
+        Self: Sized;
+}
+
+/// Marks the types that keys of type `K` can be looked up by, which encode to the same bytes as
+/// the key they stand for: every key type itself, and the strings, byte strings and byte arrays
+/// in all their owned and borrowed forms for one another.
+///
+/// A key of an unrelated type never finds anything, so lookups are bounded by this rather than
+/// by `ArtKey` to turn e.g. an integer literal that falls back to `i32` in a tree of `u64` keys
+/// into a compile error.
+pub trait KeyLookup<K>: ArtKey {}
+
+/// Marks the types that `Art::prefix_iter` scans the keys of type `K` by: anything the keys can
+/// be looked up by, and the leading elements of a tuple key.
+pub trait KeyPrefix<K>: ArtKey {}
+
+/// A deterministic automaton over the bytes of the encoded keys, which `Art::search_automaton`
+/// runs down the tree to find the keys it accepts.
+pub trait Automaton {
+    type State: Clone;
+
+    /// Returns the state before any byte has been read.
+    fn start(&self) -> Self::State;
+
+    /// Returns the state after reading `byte` in `state`.
+    fn accept(&self, state: &Self::State, byte: u8) -> Self::State;
+
+    /// Returns `true` if the bytes read so far make up a match.
+    fn is_match(&self, state: &Self::State) -> bool;
+
+    /// Returns `false` once no more bytes can lead to a match, which lets a search skip the
+    /// whole subtree below. Automata that can't tell never prune anything.
+    fn can_match(&self, _state: &Self::State) -> bool {
+        true
+    }
+
+    /// Returns the bytes that can follow in `state`, in order, or `None` if nearly any byte
+    /// can. A search looks up the children for these bytes directly instead of walking every
+    /// child of a node, the other bytes have to lead to a state that can't match.
+    fn next_bytes(&self, _state: &Self::State) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+/// An automaton matching the keys that start with a prefix.
+#[derive(Debug, Clone)]
+pub struct LiteralPrefix {
+    prefix: Vec<u8>,
+}
+
+/// An automaton matching the keys that hold the bytes of a needle in order, though not
+/// necessarily next to each other.
+#[derive(Debug, Clone)]
+pub struct Subsequence {
+    needle: Vec<u8>,
+}
+
+/// An automaton matching the keys that a regular expression matches as a whole, compiled into a
+/// DFA over bytes.
+///
+/// The supported syntax is literals, `.`, classes like `[a-z_]` and `[^0-9]`, the escapes
+/// `\d`, `\w`, `\s` and their negations `\D`, `\W`, `\S`, grouping with `(...)`, the
+/// repetitions `*`, `+` and `?`, and alternation with `|`. A `\` before any other character
+/// matches it literally.
+#[derive(Debug, Clone)]
+pub struct Regex {
+    // the transitions of every state for every byte, 256 per state. State 0 is the dead
+    // state, no byte leads out of it.
+    table: Vec<u32>,
+    accepting: Vec<bool>,
+    // whether a state can still reach an accepting one
+    live: Vec<bool>,
+}
+
+// Compiles a regex pattern into a Thompson NFA as it reads it, every part of the pattern
+// becoming a fragment with one state to enter it by and one to leave it by.
+struct RegexParser<'p> {
+    pattern: &'p [u8],
+    position: usize,
+    states: Vec<NfaState>,
+}
+
+// A state of the NFA, with the states it moves to without reading a byte and the states it
+// moves to on reading one of a set of bytes.
+struct NfaState {
+    epsilon: Vec<usize>,
+    bytes: Vec<([bool; 256], usize)>,
+}
+
+/// Builds a key out of a sequence of segments, so that keys sort by their segments in turn and
+/// a key made of the leading segments of others is a prefix of exactly those keys.
+///
+/// Every segment starts with a byte naming its type, following the FoundationDB tuple layer.
+/// Byte strings and strings escape their zero bytes and end with two of them, so a segment that
+/// is a prefix of another still sorts and scans as a separate value.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyBuilder {
+    buf: Vec<u8>,
+}
+
+/// Reads the segments of a key written by a `KeyBuilder` back, front to back.
+#[derive(Debug, Clone)]
+pub struct KeyReader<'a> {
+    bytes: &'a [u8],
+}
+
+/// A segment of a key written by a `KeyBuilder`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    Bytes(Vec<u8>),
+    Str(String),
+    I64(i64),
+    U64(u64),
+}
+
+/// An IPv4 or IPv6 network, an address with every bit past the prefix length cleared.
+///
+/// As a key it is stored a bit per byte, so that the networks inside it are the keys it is a
+/// prefix of. Networks sort by their address and then by their prefix length, the IPv4 ones
+/// first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+/// A routing table from IPv4 and IPv6 networks to values, answering which of the networks an
+/// address falls in, down to the bit.
+pub struct IpRoutingTable<V> {
+    map: Art<Cidr, V>,
+}
+
+/// Iterator over the routes of an `IpRoutingTable` in the order of their networks.
+///
+/// Constructed by `IpRoutingTable::iter` and `IpRoutingTable::covered`.
+pub struct Routes<'a, V> {
+    inner: Decoded<'a, Cidr, V>,
+}
+
+/// Iterator over the routes of an `IpRoutingTable` whose networks hold a given one, from the
+/// widest to the narrowest.
+///
+/// Constructed by `IpRoutingTable::covering`.
+pub struct CoveringRoutes<'a, V> {
+    inner: Prefixes<'a, 'static, V>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
-enum Node {
+enum Node<V> {
     None,
-    Leaf(Leaf),
-    Node4(Node4),
-    Node16(Node16),
-    Node48(Node48),
+    Leaf(Leaf<V>),
+    Node4(Node4<V>),
+    Node16(Node16<V>),
+    Node48(Node48<V>),
     //    Node48(Node48),
-    Node256(Node256),
+    Node256(Node256<V>),
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -28,50 +266,303 @@ struct NodeMeta {
 }
 
 #[derive(Debug, Clone, PartialEq)]
-struct Leaf {
+struct Leaf<V> {
     key: Vec<u8>,
-    value: Vec<u8>,
+    value: V,
 }
 
 #[derive(Debug, Clone, PartialEq)]
-struct Node4 {
+struct Node4<V> {
     meta: NodeMeta,
-    children: Vec<(u8, Node)>,
-    term_leaf: Option<Box<Node>>,
+    children: Vec<(u8, Node<V>)>,
+    term_leaf: Option<Box<Node<V>>>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
-struct Node16 {
+struct Node16<V> {
     meta: NodeMeta,
     keys: Vec<u8>,
-    children: Vec<(u8, Node)>,
-    term_leaf: Option<Box<Node>>,
+    children: Vec<(u8, Node<V>)>,
+    term_leaf: Option<Box<Node<V>>>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
-struct Node48 {
+struct Node48<V> {
     meta: NodeMeta,
     // 256, if negative then no val present
     keys: Vec<i8>,
     // 48
-    children: Vec<Node>,
-    term_leaf: Option<Box<Node>>,
+    children: Vec<Node<V>>,
+    term_leaf: Option<Box<Node<V>>>,
 }
 
 //#[derive(Debug, Clone)]
 //struct Node48 {}
 
 #[derive(Debug, Clone, PartialEq)]
-struct Node256 {
+struct Node256<V> {
     meta: NodeMeta,
-    children: Vec<Node>,
-    term_leaf: Option<Box<Node>>,
+    children: Vec<Node<V>>,
+    // the slots of children that aren't None, so that the size doesn't take a scan
+    count: usize,
+    term_leaf: Option<Box<Node<V>>>,
+}
+
+// The inner nodes from the root down to a leaf along with the position of the entry being
+// walked in each of them, lets traversals step to the neighbouring leaves in either direction.
+struct Path<P> {
+    stack: Vec<(P, usize)>,
+    leaf: Option<P>,
+}
+
+/// Iterator over the entries of an `Art` in lexicographic key order.
+pub struct Iter<'a, K, V> {
+    // the next entries to hand out from either end
+    front: Path<&'a Node<V>>,
+    back: Path<&'a Node<V>>,
+    marker: PhantomData<K>,
+}
+
+/// Iterator over the entries of an `Art` with their keys decoded back into the key type.
+///
+/// Constructed by `Iter::decoded`.
+pub struct Decoded<'a, K, V> {
+    inner: Iter<'a, K, V>,
+}
+
+/// Iterator over encoded keys decoded back into the key type of their tree.
+///
+/// Constructed by `decoded` on `Keys` and on the iterators of the set operations.
+pub struct DecodedKeys<I, K> {
+    inner: I,
+    marker: PhantomData<K>,
+}
+
+/// Iterator over the keys of an `Art` in lexicographic order.
+pub struct Keys<'a, K, V> {
+    inner: Iter<'a, K, V>,
+}
+
+/// Iterator over the values of an `Art` in key order.
+pub struct Values<'a, K, V> {
+    inner: Iter<'a, K, V>,
+}
+
+/// Iterator over the entries whose key is a prefix of an input key, from the shortest to the
+/// longest.
+///
+/// Constructed by `Art::prefixes_of`.
+pub struct Prefixes<'a, 'k, V> {
+    // the next node on the path of the input and the depth it starts at
+    node: Option<&'a Node<V>>,
+    depth: usize,
+    key: Cow<'k, [u8]>,
+}
+
+/// Iterator over the entries whose key is within a Levenshtein distance of a query, in key
+/// order along with their distance.
+///
+/// Constructed by `Art::fuzzy_search`.
+pub struct FuzzySearch<'a, V> {
+    inner: AutomatonSearch<'a, Levenshtein, V>,
+}
+
+// An automaton whose state is the row of edit distances between the key bytes read and every
+// prefix of a query, matching the keys within a number of edits of the query.
+struct Levenshtein {
+    query: Vec<u8>,
+    max_edits: usize,
+}
+
+/// Iterator over the entries whose key matches a glob pattern, in key order.
+///
+/// Constructed by `Art::glob`.
+pub struct Glob<'a, V> {
+    inner: AutomatonSearch<'a, GlobPattern, V>,
+}
+
+// A parsed glob pattern as an automaton, whose state is the positions in the pattern the key
+// bytes read can have reached.
+struct GlobPattern {
+    tokens: Vec<GlobToken>,
+}
+
+// A piece of a glob pattern matching a single byte, or any run of them for AnyRun.
+#[derive(Debug, Clone, PartialEq)]
+enum GlobToken {
+    Byte(u8),
+    AnyByte,
+    AnyRun,
+    Class {
+        negated: bool,
+        ranges: Vec<(u8, u8)>,
+    },
+}
+
+/// Iterator over the entries whose key an automaton accepts, in key order.
+///
+/// Constructed by `Art::search_automaton`.
+pub struct AutomatonSearch<'a, A: Automaton, V> {
+    automaton: A,
+    // the nodes left to visit, with the depth they start at and the state of the automaton
+    // after the key bytes above them
+    stack: Vec<(&'a Node<V>, usize, A::State)>,
+}
+
+/// The nodes of an `Art`, whose `Debug` output dumps the tree as it is laid out rather than
+/// the entries it holds.
+///
+/// Constructed by `Art::structure`.
+pub struct Structure<'a, V> {
+    root: &'a Node<V>,
+}
+
+/// Iterator over the entries of an `Art` in key order, with mutable references to the values.
+pub struct IterMut<'a, V> {
+    // the entry to hand out next
+    path: Path<RawNode<'a, V>>,
+}
+
+/// Iterator that consumes an `Art`, handing out its keys decoded back into `K` along with the
+/// values in key order.
+pub struct IntoIter<K, V> {
+    // the inner nodes from the root down to the current leaf, taken out of their parents, each
+    // with the position to resume its entries from
+    stack: Vec<(Node<V>, usize)>,
+    marker: PhantomData<K>,
+}
+
+// Two trees walked side by side in key order. Either side can be moved ahead to the other
+// one's key, passing over the subtrees in between without visiting their leaves.
+struct Lockstep<'a, K, V, W> {
+    a_root: &'a Node<V>,
+    a: Path<&'a Node<V>>,
+    b_root: &'a Node<W>,
+    b: Path<&'a Node<W>>,
+    // the steps taken, each landing either side on at most one new leaf, for the tests to
+    // check that the subtrees in between are passed over
+    #[cfg(test)]
+    steps: usize,
+    marker: PhantomData<K>,
+}
+
+/// Iterator over the keys in either of two trees, in order.
+///
+/// Constructed by `Art::union` and `ArtSet::union`.
+pub struct Union<'a, K, V, W> {
+    inner: Lockstep<'a, K, V, W>,
+}
+
+/// Iterator over the keys in both of two trees, in order.
+///
+/// Constructed by `Art::intersection` and `ArtSet::intersection`.
+pub struct Intersection<'a, K, V, W> {
+    inner: Lockstep<'a, K, V, W>,
+}
+
+/// Iterator over the keys in one tree but not in another, in order.
+///
+/// Constructed by `Art::difference` and `ArtSet::difference`.
+pub struct Difference<'a, K, V, W> {
+    inner: Lockstep<'a, K, V, W>,
+}
+
+/// Iterator over the keys in exactly one of two trees, in order.
+///
+/// Constructed by `Art::symmetric_difference` and `ArtSet::symmetric_difference`.
+pub struct SymmetricDifference<'a, K, V, W> {
+    inner: Lockstep<'a, K, V, W>,
+}
+
+/// A position in an `Art` that can be moved to a key and stepped in either direction.
+///
+/// A cursor that steps past either end, or seeks to a key with nothing on that side of it,
+/// becomes unpositioned and stays so until it is seeked again.
+pub struct Cursor<'a, K, V> {
+    root: &'a Node<V>,
+    path: Path<&'a Node<V>>,
+    marker: PhantomData<K>,
+}
+
+/// A cursor like `Cursor` that can also modify the entries of the `Art` it walks.
+///
+/// Removing or inserting through the cursor only touches the nodes below the point where the
+/// change happens, the traversal carries on from there instead of starting over at the root.
+pub struct CursorMut<'a, K, V> {
+    root: RawNode<'a, V>,
+    size: &'a mut usize,
+    path: Path<RawNode<'a, V>>,
+    marker: PhantomData<K>,
+}
+
+/// A view into a single entry of an `Art`, which may either be vacant or occupied.
+///
+/// Constructed by `Art::entry`.
+pub enum Entry<'a, K, V> {
+    Vacant(VacantEntry<'a, K, V>),
+    Occupied(OccupiedEntry<'a, K, V>),
+}
+
+/// A view into a vacant entry of an `Art`, part of the `Entry` enum.
+pub struct VacantEntry<'a, K, V> {
+    key: K,
+    // the encoding of the key when it had to be built for the descent, so that inserting
+    // doesn't build it again. A key that is its own encoding hands its bytes over instead.
+    encoded: Option<Vec<u8>>,
+    // the node the key goes into, whose compressed path starts at depth. Inserting only has to
+    // add a child, split the leaf or split the prefix of this node.
+    node: &'a mut Node<V>,
+    depth: usize,
+    size: &'a mut usize,
+}
+
+/// A view into an occupied entry of an `Art`, part of the `Entry` enum.
+pub struct OccupiedEntry<'a, K, V> {
+    key: K,
+    // the parent of the leaf along with the key char the leaf hangs off, so that the leaf can
+    // be removed from it. The leaf itself when it is the root, with no key char.
+    node: &'a mut Node<V>,
+    key_char: Option<Option<u8>>,
+    size: &'a mut usize,
+}
+
+// A node of a tree that is mutably borrowed by a CursorMut. The cursor keeps pointers to all
+// the nodes on its path at once, which references can't express.
+struct RawNode<'a, V> {
+    ptr: NonNull<Node<V>>,
+    marker: PhantomData<&'a mut Node<V>>,
 }
 
 mod art;
+mod automaton;
+mod composite;
+mod cursor;
+mod entry;
+mod error;
+mod iter;
+mod key;
 mod leaf;
 mod node;
 mod node16;
 mod node256;
 mod node48;
 mod node4;
+mod path;
+mod routing;
+mod search;
+mod set;
+
+#[cfg(test)]
+mod tests {
+    // a linear congruential generator for the randomized tests, handing out its whole state so
+    // that each test picks the bits it needs
+    pub(crate) fn _lcg(seed: u64) -> impl FnMut() -> u64 {
+        let mut state = seed;
+        move || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            state
+        }
+    }
+}
@@ -1,6 +1,5 @@
 use art_rs::Art;
 use std::fs::File;
-use std::io;
 use std::io::{stdin, BufRead, BufReader};
 
 const SEARCH_LIMIT: usize = 1000;
@@ -11,7 +10,7 @@ pub fn main() {
     let input = File::open(PATH).unwrap();
     let input = BufReader::new(input);
     for line in input.lines() {
-        let st = line.unwrap().as_bytes().to_vec();
+        let st = line.unwrap().into_bytes();
         map.insert(st.clone(), st);
     }
 
@@ -26,8 +25,7 @@ pub fn main() {
             break;
         }
         let st = line.unwrap();
-        let st = st.as_bytes().to_vec();
-        let res = map.search(&st);
+        map.search(st.as_str());
     }
     println!("Finished searching");
     let mut buffer = String::new();
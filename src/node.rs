@@ -1,24 +1,21 @@
-use crate::{Node, Node16, Node256, Node48, NodeMeta, MAX_PREFIX};
-use std::borrow::{Borrow, BorrowMut};
+use crate::{ArtError, Leaf, Node, Node16, Node256, Node4, Node48, NodeMeta, MAX_PREFIX};
 use std::cmp::min;
-use std::fmt::{Display, Error, Formatter};
+use std::fmt::{Debug, Display, Error, Formatter};
 use std::mem::replace;
-use xi_rope::compare::{ne_idx, ne_idx_rev};
+use std::ptr::{self, NonNull};
 
-impl Node {
+impl<V> Node<V> {
+    // the byte a key is stored under at depth, None when the key ends there and it
+    // becomes the term leaf
     pub(crate) fn key_char(key: &[u8], depth: usize) -> Option<u8> {
-        if key.len() - 1 < depth {
-            None
-        } else {
-            Some(key[depth])
-        }
+        key.get(depth).copied()
     }
 
     fn match_key(&self, key: &[u8], max_match_len: usize, depth: usize) -> Option<usize> {
-        let meta = self.get_meta();
+        let partial = self.partial();
         let mut idx = 0;
         while idx < max_match_len {
-            if meta.partial[idx] != key[depth + idx] {
+            if partial[idx] != key[depth + idx] {
                 return Some(idx);
             }
             idx += 1;
@@ -28,7 +25,10 @@ impl Node {
 
     pub(crate) fn prefix_match(&self, key: &[u8], depth: usize) -> usize {
         // match from depth..max_match_len
-        let max_match_len = min(min(MAX_PREFIX, self.partial().len()), key.len() - depth);
+        let max_match_len = min(
+            min(MAX_PREFIX, self.partial().len()),
+            key.len().saturating_sub(depth),
+        );
         self.match_key(key, max_match_len, depth).unwrap_or(0)
     }
 
@@ -37,10 +37,13 @@ impl Node {
         if mismatch_idx < MAX_PREFIX {
             mismatch_idx
         } else {
-            // find leaf following the minimum node (None key)
-            let leaf = self.minimum();
-            if let Node::Leaf(leaf) = leaf {
-                let limit = min(leaf.key.len(), key.len()) - depth;
+            // find leaf following the minimum node (None key), only the bytes of the prefix
+            // count, the leaf goes on past it
+            if let Some(leaf) = self.minimum() {
+                let limit = min(
+                    self.prefix_len(),
+                    min(leaf.key.len(), key.len()).saturating_sub(depth),
+                );
                 while mismatch_idx < limit {
                     if leaf.key[mismatch_idx + depth] != key[mismatch_idx + depth] {
                         break;
@@ -54,74 +57,151 @@ impl Node {
         }
     }
 
-    pub(crate) fn minimum(&self) -> &Node {
+    pub(crate) fn minimum(&self) -> Option<&Leaf<V>> {
         let mut tmp_node = self;
         loop {
             match tmp_node {
-                Node::Leaf(_) => {
-                    return tmp_node;
+                Node::Leaf(leaf) => {
+                    return Some(leaf);
                 }
+                // empty tree
                 Node::None => {
-                    panic!("Should not be here");
+                    return None;
                 }
                 node => {
                     // if we have a node at term_leaf, assign tmp_node to that and continue
                     // else use the first element in the children list
-                    if node.term_leaf().is_some() {
-                        tmp_node = node.term_leaf().as_ref().unwrap();
+                    if let Some(term_leaf) = node.term_leaf() {
+                        tmp_node = term_leaf;
                     } else {
-                        tmp_node = node.first();
+                        tmp_node = node.first()?;
                     }
                 }
             }
         }
     }
 
-    pub(crate) fn set_prefix_len(&mut self, new_prefix_len: usize) {
-        self.get_meta_mut().prefix_len = new_prefix_len;
+    pub(crate) fn set_prefix_len(&mut self, new_prefix_len: usize) -> Result<(), ArtError> {
+        self.get_meta_mut()?.prefix_len = new_prefix_len;
+        Ok(())
     }
 
-    pub(crate) fn set_partial(&mut self, new_partial: Vec<u8>) {
-        self.get_meta_mut().partial = new_partial;
+    pub(crate) fn set_partial(&mut self, new_partial: Vec<u8>) -> Result<(), ArtError> {
+        self.get_meta_mut()?.partial = new_partial;
+        Ok(())
     }
 
-    pub(crate) fn add_child(&mut self, node: Node, key_char: Option<u8>) {
+    pub(crate) fn add_child(
+        &mut self,
+        node: Node<V>,
+        key_char: Option<u8>,
+    ) -> Result<(), ArtError> {
         match self {
             Node::Node4(node4) => {
                 if node4.should_grow() {
-                    let mut node16 = Node::Node16(Node16::new());
+                    let node16 = Node::Node16(Node16::try_new()?);
                     let old_node = replace(self, node16);
-                    self.copy(old_node);
-                    self.add_child(node, key_char);
+                    self.copy(old_node)?;
+                    self.add_child(node, key_char)
                 } else {
-                    node4.add_child(node, key_char);
+                    node4.add_child(node, key_char)
                 }
             }
             Node::Node16(node16) => {
                 if node16.should_grow() {
-                    let mut node48 = Node::Node48(Node48::new());
+                    let node48 = Node::Node48(Node48::try_new()?);
                     let old_node = replace(self, node48);
-                    self.copy(old_node);
-                    self.add_child(node, key_char);
+                    self.copy(old_node)?;
+                    self.add_child(node, key_char)
                 } else {
-                    node16.add_child(node, key_char);
+                    node16.add_child(node, key_char)
                 }
             }
             Node::Node48(node48) => {
                 if node48.should_grow() {
-                    let mut node256 = Node::Node256(Node256::new());
+                    let node256 = Node::Node256(Node256::try_new()?);
                     let old_node = replace(self, node256);
-                    self.copy(old_node);
-                    self.add_child(node, key_char);
+                    self.copy(old_node)?;
+                    self.add_child(node, key_char)
                 } else {
-                    node48.add_child(node, key_char);
+                    node48.add_child(node, key_char)
                 }
             }
             Node::Node256(node256) => node256.add_child(node, key_char),
-            _ => unimplemented!(),
+            _ => Err(ArtError::Corrupted(
+                "children can only be added to inner nodes",
+            )),
+        }
+    }
+
+    pub(crate) fn remove_child(
+        &mut self,
+        key_char: Option<u8>,
+    ) -> Result<Option<Node<V>>, ArtError> {
+        let removed = match self {
+            Node::Node4(node4) => node4.remove_child(key_char),
+            Node::Node16(node16) => node16.remove_child(key_char),
+            Node::Node48(node48) => node48.remove_child(key_char),
+            Node::Node256(node256) => node256.remove_child(key_char),
+            _ => {
+                return Err(ArtError::Corrupted(
+                    "children can only be removed from inner nodes",
+                ))
+            }
+        };
+        if removed.is_some() {
+            self.shrink()?;
+        }
+        Ok(removed)
+    }
+
+    // inverse of the growth in add_child, drop down a node type once the children fit
+    // in the smaller one. A node4 left with a single entry is replaced by that entry.
+    fn shrink(&mut self) -> Result<(), ArtError> {
+        match self {
+            Node::Node4(node4) if node4.should_collapse() => {
+                let (key_char, child) = node4
+                    .take_only_child()
+                    .ok_or(ArtError::Corrupted("node4 without children"))?;
+                let old_node = replace(self, child);
+                self.merge_prefix(&old_node, key_char)
+            }
+            Node::Node16(node16) if node16.should_shrink() => {
+                let old_node = replace(self, Node::Node4(Node4::try_new()?));
+                self.copy(old_node)
+            }
+            Node::Node48(node48) if node48.should_shrink() => {
+                let old_node = replace(self, Node::Node16(Node16::try_new()?));
+                self.copy(old_node)
+            }
+            Node::Node256(node256) if node256.should_shrink() => {
+                let old_node = replace(self, Node::Node48(Node48::try_new()?));
+                self.copy(old_node)
+            }
+            _ => Ok(()),
         }
     }
 
+    // prepend the prefix of the collapsed parent and the byte leading to this node, leaves
+    // carry the full key so there is nothing to do for them.
+    fn merge_prefix(&mut self, parent: &Node<V>, key_char: Option<u8>) -> Result<(), ArtError> {
+        if let Node::Leaf(_) = self {
+            return Ok(());
+        }
+        let key_char = key_char.ok_or(ArtError::Corrupted(
+            "only leaves can hang off the term leaf",
+        ))?;
+
+        let mut new_partial: Vec<u8> = parent.partial().to_vec();
+        new_partial.push(key_char);
+        new_partial.extend_from_slice(self.partial());
+        new_partial.truncate(MAX_PREFIX);
+
+        let new_prefix_len = parent.prefix_len() + 1 + self.prefix_len();
+        self.set_prefix_len(new_prefix_len)?;
+        self.set_partial(new_partial)
+    }
+
     pub(crate) fn child_exists(&self, key: &[u8], depth: usize) -> bool {
         if let Some(key_char) = key.get(depth) {
             self.child_at(*key_char).is_some()
@@ -132,94 +212,89 @@ impl Node {
         }
     }
 
-    pub(crate) fn find_child(&self, key: &[u8], depth: usize) -> Option<&Node> {
+    pub(crate) fn find_child(&self, key: &[u8], depth: usize) -> Option<&Node<V>> {
         if let Some(ch) = key.get(depth) {
             self.child_at(*ch)
         } else if depth == key.len() {
-            if let Some(child_node) = &self.term_leaf() {
-                Some(child_node)
-            } else {
-                None
-            }
+            self.term_leaf()
         } else {
             None
         }
     }
 
-    pub(crate) fn find_child_mut(&mut self, key: &[u8], depth: usize) -> Option<&mut Node> {
+    pub(crate) fn find_child_mut(&mut self, key: &[u8], depth: usize) -> Option<&mut Node<V>> {
         if let Some(ch) = key.get(depth) {
             self.child_at_mut(*ch)
         } else if key.len() == depth {
-            if self.term_leaf().is_some() {
-                Some(self.term_leaf_mut().unwrap())
-            } else {
-                None
-            }
+            self.term_leaf_mut().map(|term_leaf| term_leaf.as_mut())
         } else {
             None
         }
     }
 
-    fn get_meta(&self) -> &NodeMeta {
-        match self {
-            Node::Node4(node4) => &node4.meta,
-            Node::Node16(node16) => &node16.meta,
-            Node::Node48(node48) => &node48.meta,
-            Node::Node256(node256) => &node256.meta,
-            _ => {
-                panic!("Prefix len is not applicable for node of this type");
-            }
+    // the child for a key char handed to add_child, None being the term leaf
+    pub(crate) fn child(&self, key_char: Option<u8>) -> Option<&Node<V>> {
+        match key_char {
+            Some(key_char) => self.child_at(key_char),
+            None => self.term_leaf(),
         }
     }
 
-    fn get_meta_mut(&mut self) -> &mut NodeMeta {
+    pub(crate) fn child_mut(&mut self, key_char: Option<u8>) -> Option<&mut Node<V>> {
+        match key_char {
+            Some(key_char) => self.child_at_mut(key_char),
+            None => self.term_leaf_mut().map(|term_leaf| term_leaf.as_mut()),
+        }
+    }
+
+    fn get_meta_mut(&mut self) -> Result<&mut NodeMeta, ArtError> {
         match self {
-            Node::Node4(node4) => &mut node4.meta,
-            Node::Node16(node16) => &mut node16.meta,
-            Node::Node48(node48) => &mut node48.meta,
-            Node::Node256(node256) => &mut node256.meta,
-            _ => {
-                panic!("Prefix len is not applicable for node of this type");
-            }
+            Node::Node4(node4) => Ok(&mut node4.meta),
+            Node::Node16(node16) => Ok(&mut node16.meta),
+            Node::Node48(node48) => Ok(&mut node48.meta),
+            Node::Node256(node256) => Ok(&mut node256.meta),
+            _ => Err(ArtError::Corrupted(
+                "prefix is not applicable for node of this type",
+            )),
         }
     }
 
-    pub(crate) fn term_leaf(&self) -> Option<&Box<Node>> {
+    pub(crate) fn term_leaf(&self) -> Option<&Node<V>> {
         match self {
             Node::Node4(node4) => node4.term_leaf(),
             Node::Node16(node16) => node16.term_leaf(),
             Node::Node48(node48) => node48.term_leaf(),
             Node::Node256(node256) => node256.term_leaf(),
-            _ => unimplemented!(),
+            _ => None,
         }
     }
 
-    pub(crate) fn term_leaf_mut(&mut self) -> Option<&mut Box<Node>> {
+    pub(crate) fn term_leaf_mut(&mut self) -> Option<&mut Box<Node<V>>> {
         match self {
             Node::Node4(node4) => node4.term_leaf_mut(),
             Node::Node16(node16) => node16.term_leaf_mut(),
             Node::Node48(node48) => node48.term_leaf_mut(),
             Node::Node256(node256) => node256.term_leaf_mut(),
-            _ => unimplemented!(),
+            _ => None,
         }
     }
 
-    pub(crate) fn child_at(&self, key: u8) -> Option<&Node> {
+    pub(crate) fn child_at(&self, key: u8) -> Option<&Node<V>> {
         match self {
             Node::Node4(node4) => node4.child_at(key),
             Node::Node16(node16) => node16.child_at(key),
             Node::Node48(node48) => node48.child_at(key),
             Node::Node256(node256) => node256.child_at(key),
-            _ => unimplemented!(),
+            _ => None,
         }
     }
-    pub(crate) fn child_at_mut(&mut self, key: u8) -> Option<&mut Node> {
+    pub(crate) fn child_at_mut(&mut self, key: u8) -> Option<&mut Node<V>> {
         match self {
             Node::Node4(node4) => node4.child_at_mut(key),
             Node::Node16(node16) => node16.child_at_mut(key),
             Node::Node48(node48) => node48.child_at_mut(key),
             Node::Node256(node256) => node256.child_at_mut(key),
-            _ => unimplemented!(),
+            _ => None,
         }
     }
 
@@ -229,7 +304,7 @@ impl Node {
             Node::Node16(node16) => node16.prefix_len(),
             Node::Node48(node48) => node48.prefix_len(),
             Node::Node256(node256) => node256.prefix_len(),
-            _ => unimplemented!(),
+            _ => 0,
         }
     }
 
@@ -239,42 +314,283 @@ impl Node {
             Node::Node16(node16) => node16.partial(),
             Node::Node48(node48) => node48.partial(),
             Node::Node256(node256) => node256.partial(),
-            _ => unimplemented!(),
+            _ => &[],
         }
     }
 
-    pub(crate) fn children(&self) -> Vec<(Option<u8>, &Node)> {
+    // the children with their key bytes in key order, followed by the term leaf
+    #[cfg(test)]
+    pub(crate) fn children(&self) -> Vec<(Option<u8>, &Node<V>)> {
         match self {
             Node::Node4(node4) => node4.children(),
             Node::Node16(node16) => node16.children(),
             Node::Node48(node48) => node48.children(),
             Node::Node256(node256) => node256.children(),
-            _ => unimplemented!(),
+            _ => Vec::new(),
         }
     }
 
-    pub(crate) fn first(&self) -> &Node {
+    pub(crate) fn first(&self) -> Option<&Node<V>> {
         match self {
             Node::Node4(node4) => node4.first(),
             Node::Node16(node16) => node16.first(),
             Node::Node48(node48) => node48.first(),
             Node::Node256(node256) => node256.first(),
-            _ => unimplemented!(),
+            _ => None,
+        }
+    }
+
+    // entries of a node are addressed by position: 0 is the term leaf, the key that ends at
+    // this node, and 1 + slot are the children. The slot is the index into the sorted
+    // children for node4 and node16 and the key byte for node48 and node256, so walking
+    // positions upwards visits the entries in key order.
+    pub(crate) fn next_entry(&self, from: usize) -> Option<(usize, &Node<V>)> {
+        if from == 0 {
+            if let Some(term_leaf) = self.term_leaf() {
+                return Some((0, term_leaf));
+            }
+        }
+        let slot = from.saturating_sub(1);
+        let child = match self {
+            Node::Node4(node4) => node4.next_child(slot),
+            Node::Node16(node16) => node16.next_child(slot),
+            Node::Node48(node48) => node48.next_child(slot),
+            Node::Node256(node256) => node256.next_child(slot),
+            _ => None,
+        };
+        child.map(|(slot, child)| (slot + 1, child))
+    }
+
+    // last entry positioned before `before`, the term leaf comes after all the children when
+    // walking backwards
+    pub(crate) fn prev_entry(&self, before: usize) -> Option<(usize, &Node<V>)> {
+        if before > 1 {
+            let child = match self {
+                Node::Node4(node4) => node4.prev_child(before - 1),
+                Node::Node16(node16) => node16.prev_child(before - 1),
+                Node::Node48(node48) => node48.prev_child(before - 1),
+                Node::Node256(node256) => node256.prev_child(before - 1),
+                _ => None,
+            };
+            if let Some((slot, child)) = child {
+                return Some((slot + 1, child));
+            }
+        }
+        if before > 0 {
+            if let Some(term_leaf) = self.term_leaf() {
+                return Some((0, term_leaf));
+            }
+        }
+        None
+    }
+
+    // the entry at a position handed out by next_entry or prev_entry
+    pub(crate) fn entry_mut(&mut self, position: usize) -> Option<&mut Node<V>> {
+        if position == 0 {
+            return self.term_leaf_mut().map(|term_leaf| term_leaf.as_mut());
+        }
+        let slot = position - 1;
+        match self {
+            Node::Node4(node4) => node4.children.get_mut(slot).map(|child| &mut child.1),
+            Node::Node16(node16) => node16.children.get_mut(slot).map(|child| &mut child.1),
+            Node::Node48(_) | Node::Node256(_) => self.child_at_mut(slot as u8),
+            _ => None,
+        }
+    }
+
+    // the key byte of the child at a position handed out by next_entry or prev_entry, None for
+    // the term leaf
+    pub(crate) fn key_char_at(&self, position: usize) -> Option<u8> {
+        let slot = position.checked_sub(1)?;
+        match self {
+            Node::Node4(node4) => node4.children.get(slot).map(|child| child.0),
+            Node::Node16(node16) => node16.children.get(slot).map(|child| child.0),
+            Node::Node48(_) | Node::Node256(_) => Some(slot as u8),
+            _ => None,
+        }
+    }
+
+    // The same walk as next_entry, prev_entry and seek_child over raw pointers, for the mutable
+    // cursor and IterMut. An entry is reached through the header of the field holding it, so no
+    // reference ever spans the other entries, which IterMut may have handed out values from.
+    //
+    // Safety: node points to a live node, and nothing borrows the node or the entries the walk
+    // reads for as long as it runs.
+
+    pub(crate) unsafe fn next_entry_ptr(
+        node: NonNull<Node<V>>,
+        from: usize,
+    ) -> Option<(usize, NonNull<Node<V>>)> {
+        let node = node.as_ptr();
+        if from == 0 {
+            if let Some(term_leaf) = Self::term_leaf_ptr(node) {
+                return Some((0, term_leaf));
+            }
+        }
+        (from.saturating_sub(1)..Self::slot_count(node))
+            .find_map(|slot| Some((slot + 1, Self::child_ptr(node, slot)?)))
+    }
+
+    pub(crate) unsafe fn prev_entry_ptr(
+        node: NonNull<Node<V>>,
+        before: usize,
+    ) -> Option<(usize, NonNull<Node<V>>)> {
+        let node = node.as_ptr();
+        if before > 1 {
+            let child = (0..min(before - 1, Self::slot_count(node)))
+                .rev()
+                .find_map(|slot| Some((slot + 1, Self::child_ptr(node, slot)?)));
+            if child.is_some() {
+                return child;
+            }
+        }
+        if before > 0 {
+            return Self::term_leaf_ptr(node).map(|term_leaf| (0, term_leaf));
+        }
+        None
+    }
+
+    pub(crate) unsafe fn seek_child_ptr(
+        node: NonNull<Node<V>>,
+        key_char: u8,
+    ) -> (usize, Option<NonNull<Node<V>>>) {
+        let node = node.as_ptr();
+        let (slot, child) = match *node {
+            Node::Node4(Node4 {
+                ref mut children, ..
+            })
+            | Node::Node16(Node16 {
+                ref mut children, ..
+            }) => {
+                // the children are sorted by their key byte, which is read on its own
+                let (first, len) = (children.as_mut_ptr(), children.len());
+                let slot = (0..len)
+                    .find(|slot| (*first.add(*slot)).0 >= key_char)
+                    .unwrap_or(len);
+                let child = if slot < len && (*first.add(slot)).0 == key_char {
+                    NonNull::new(ptr::addr_of_mut!((*first.add(slot)).1))
+                } else {
+                    None
+                };
+                (slot, child)
+            }
+            Node::Node48(_) | Node::Node256(_) => {
+                (key_char as usize, Self::child_ptr(node, key_char as usize))
+            }
+            _ => (0, None),
+        };
+        (slot + 1, child)
+    }
+
+    // how far the slots of the children go, the sorted ones for node4 and node16 and the key
+    // bytes for node48 and node256
+    unsafe fn slot_count(node: *mut Node<V>) -> usize {
+        match *node {
+            Node::Node4(Node4 { ref children, .. }) | Node::Node16(Node16 { ref children, .. }) => {
+                children.len()
+            }
+            Node::Node48(_) | Node::Node256(_) => 256,
+            _ => 0,
+        }
+    }
+
+    unsafe fn child_ptr(node: *mut Node<V>, slot: usize) -> Option<NonNull<Node<V>>> {
+        let child = match *node {
+            Node::Node4(Node4 {
+                ref mut children, ..
+            })
+            | Node::Node16(Node16 {
+                ref mut children, ..
+            }) => {
+                if slot >= children.len() {
+                    return None;
+                }
+                ptr::addr_of_mut!((*children.as_mut_ptr().add(slot)).1)
+            }
+            Node::Node48(Node48 {
+                ref keys,
+                ref mut children,
+                ..
+            }) => {
+                let index = *keys.get(slot)?;
+                if index < 0 || index as usize >= children.len() {
+                    return None;
+                }
+                children.as_mut_ptr().add(index as usize)
+            }
+            Node::Node256(Node256 {
+                ref mut children, ..
+            }) => {
+                if slot >= children.len() {
+                    return None;
+                }
+                let child = children.as_mut_ptr().add(slot);
+                if matches!(*child, Node::None) {
+                    return None;
+                }
+                child
+            }
+            _ => return None,
+        };
+        NonNull::new(child)
+    }
+
+    unsafe fn term_leaf_ptr(node: *mut Node<V>) -> Option<NonNull<Node<V>>> {
+        let term_leaf = match *node {
+            Node::Node4(Node4 {
+                ref mut term_leaf, ..
+            })
+            | Node::Node16(Node16 {
+                ref mut term_leaf, ..
+            })
+            | Node::Node48(Node48 {
+                ref mut term_leaf, ..
+            })
+            | Node::Node256(Node256 {
+                ref mut term_leaf, ..
+            }) => term_leaf.as_mut()?,
+            _ => return None,
+        };
+        NonNull::new(ptr::addr_of_mut!(**term_leaf))
+    }
+
+    // position of the child for key_char, or of the slot it would take when absent, so
+    // that the entries after it can be walked with next_entry
+    pub(crate) fn seek_child(&self, key_char: u8) -> (usize, Option<&Node<V>>) {
+        let (slot, child) = match self {
+            Node::Node4(node4) => node4.seek_child(key_char),
+            Node::Node16(node16) => node16.seek_child(key_char),
+            Node::Node48(node48) => (key_char as usize, node48.child_at(key_char)),
+            Node::Node256(node256) => (key_char as usize, node256.child_at(key_char)),
+            _ => (0, None),
+        };
+        (slot + 1, child)
+    }
+
+    // the whole compressed path of the node starting at depth, partial only holds the first
+    // MAX_PREFIX bytes so longer paths are read off the minimum leaf
+    pub(crate) fn full_prefix(&self, depth: usize) -> &[u8] {
+        if self.prefix_len() <= MAX_PREFIX {
+            return self.partial();
+        }
+        match self.minimum() {
+            Some(leaf) => &leaf.key[depth..depth + self.prefix_len()],
+            None => self.partial(),
         }
     }
 
-    pub(crate) fn copy(&mut self, node_to_copy: Node) {
+    pub(crate) fn copy(&mut self, node_to_copy: Node<V>) -> Result<(), ArtError> {
         match self {
-            Node::Node4(node4) => panic!("should not be here"),
+            Node::Node4(node4) => node4.copy(node_to_copy),
             Node::Node16(node16) => node16.copy(node_to_copy),
             Node::Node48(node48) => node48.copy(node_to_copy),
             Node::Node256(node256) => node256.copy(node_to_copy),
-            _ => unimplemented!(),
+            _ => Err(ArtError::Corrupted("only inner nodes can be copied into")),
         }
     }
 }
 
-impl Display for Node {
+impl<V: Debug> Display for Node<V> {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
         match self {
             Node::Node4(node4) => write!(f, "{}", node4),
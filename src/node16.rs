@@ -1,28 +1,61 @@
-use crate::{Node, Node16, NodeMeta, MAX_PREFIX};
+use crate::{ArtError, Node, Node16, NodeMeta, MAX_PREFIX};
 use std::borrow::{Borrow, BorrowMut};
+use std::cmp::min;
 use std::fmt::{Display, Error, Formatter};
+use std::mem::replace;
 
-impl Node16 {
+impl<V> Node16<V> {
+    #[cfg(test)]
     pub(crate) fn new() -> Self {
-        Node16 {
+        Self::try_new().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub(crate) fn try_new() -> Result<Self, ArtError> {
+        let mut partial = Vec::new();
+        partial.try_reserve_exact(MAX_PREFIX)?;
+        let mut keys = Vec::new();
+        keys.try_reserve_exact(32)?;
+        keys.resize(32, 0);
+        let mut children = Vec::new();
+        children.try_reserve_exact(16)?;
+        Ok(Node16 {
             meta: NodeMeta {
                 prefix_len: 0,
-                partial: Vec::with_capacity(MAX_PREFIX),
+                partial,
             },
-            keys: Vec::with_capacity(16),
-            children: Vec::with_capacity(16),
+            keys,
+            children,
             term_leaf: None,
-        }
+        })
     }
 
-    pub(crate) fn copy(&mut self, node_to_copy: Node) {
+    pub(crate) fn copy(&mut self, node_to_copy: Node<V>) -> Result<(), ArtError> {
         match node_to_copy {
             Node::Node4(node4) => {
                 self.meta = node4.meta;
                 self.children = node4.children;
                 self.term_leaf = node4.term_leaf;
+                self.sync_keys();
+                Ok(())
             }
-            _ => panic!("only copying from node4 is allowed"),
+            Node::Node48(mut node48) => {
+                self.meta = node48.meta;
+                self.term_leaf = node48.term_leaf;
+
+                // node48 keeps its children in insertion order, walk the key index to keep
+                // them sorted here
+                for (key, key_index) in node48.keys.iter().enumerate() {
+                    if *key_index >= 0 {
+                        let child = replace(&mut node48.children[*key_index as usize], Node::None);
+                        self.children.push((key as u8, child));
+                    }
+                }
+                self.sync_keys();
+                Ok(())
+            }
+            _ => Err(ArtError::Corrupted(
+                "only copying from node4 or node48 is allowed",
+            )),
         }
     }
 
@@ -30,24 +63,50 @@ impl Node16 {
         self.children.len() == 16
     }
 
+    pub(crate) fn should_shrink(&self) -> bool {
+        self.len() <= 3
+    }
+
+    // the key index is padded to 32 bytes so the avx lookup can load it whole
+    fn sync_keys(&mut self) {
+        self.keys = vec![0u8; 32];
+        for (index, (key, _)) in self.children.iter().enumerate() {
+            self.keys[index] = *key;
+        }
+    }
+
     // TODO ===================== Refactor and share between Node4 and Node16 =====
 
-    pub(crate) fn add_child(&mut self, node: Node, key_char: Option<u8>) {
+    pub(crate) fn add_child(
+        &mut self,
+        node: Node<V>,
+        key_char: Option<u8>,
+    ) -> Result<(), ArtError> {
         match key_char {
             Some(current_char) => {
+                self.children.try_reserve(1)?;
                 self.children.push((current_char, node));
-                self.children.sort_unstable_by(|a, b| a.0.cmp(&b.0));
-
-                self.keys = vec![0u8; 16];
-                for x in self.keys().iter().enumerate() {
-                    self.keys[x.0] = *x.1;
-                }
+                self.children.sort_unstable_by_key(|a| a.0);
+                self.sync_keys();
             }
             None => {
                 // key char would be None in the case of leaf nodes.
                 self.term_leaf = Some(Box::new(node))
             }
         }
+        Ok(())
+    }
+
+    pub(crate) fn remove_child(&mut self, key_char: Option<u8>) -> Option<Node<V>> {
+        match key_char {
+            Some(current_char) => {
+                let index = self.find_index(current_char)?;
+                let (_, child) = self.children.remove(index);
+                self.sync_keys();
+                Some(child)
+            }
+            None => self.term_leaf.take().map(|leaf| *leaf),
+        }
     }
 
     pub(crate) fn len(&self) -> usize {
@@ -58,12 +117,28 @@ impl Node16 {
         self.children.len() + leaf_count
     }
 
-    pub(crate) fn first(&self) -> &Node {
-        self.children.first().unwrap().1.borrow()
+    pub(crate) fn first(&self) -> Option<&Node<V>> {
+        self.children.first().map(|child| child.1.borrow())
+    }
+
+    pub(crate) fn next_child(&self, from: usize) -> Option<(usize, &Node<V>)> {
+        self.children.get(from).map(|child| (from, &child.1))
+    }
+
+    pub(crate) fn prev_child(&self, before: usize) -> Option<(usize, &Node<V>)> {
+        let index = min(before, self.children.len()).checked_sub(1)?;
+        Some((index, &self.children[index].1))
     }
 
-    pub(crate) fn children(&self) -> Vec<(Option<u8>, &Node)> {
-        let mut res: Vec<(Option<u8>, &Node)> =
+    pub(crate) fn seek_child(&self, key: u8) -> (usize, Option<&Node<V>>) {
+        match self.children.binary_search_by_key(&key, |child| child.0) {
+            Ok(index) => (index, Some(&self.children[index].1)),
+            Err(index) => (index, None),
+        }
+    }
+
+    pub(crate) fn children(&self) -> Vec<(Option<u8>, &Node<V>)> {
+        let mut res: Vec<(Option<u8>, &Node<V>)> =
             self.children.iter().map(|n| (Some(n.0), &n.1)).collect();
         if self.term_leaf().is_some() {
             res.push((None, self.term_leaf.as_ref().unwrap()));
@@ -75,12 +150,12 @@ impl Node16 {
         self.children.iter().map(|i| i.0).collect()
     }
 
-    pub(crate) fn term_leaf_mut(&mut self) -> Option<&mut Box<Node>> {
+    pub(crate) fn term_leaf_mut(&mut self) -> Option<&mut Box<Node<V>>> {
         self.term_leaf.as_mut()
     }
 
-    pub(crate) fn term_leaf(&self) -> Option<&Box<Node>> {
-        self.term_leaf.as_ref()
+    pub(crate) fn term_leaf(&self) -> Option<&Node<V>> {
+        self.term_leaf.as_deref()
     }
 
     pub(crate) fn partial(&self) -> &[u8] {
@@ -99,10 +174,11 @@ impl Node16 {
         let keys = _mm256_loadu_si256(self.keys.as_slice().as_ptr() as *const _);
         let cmp = _mm256_cmpeq_epi8(key, keys);
         let mask = _mm256_movemask_epi8(cmp);
-        let tz = mask.trailing_zeros();
+        let tz = mask.trailing_zeros() as usize;
 
-        if tz < 31 {
-            Some(tz as usize)
+        // slots past the last child are zero padded, ignore matches there
+        if tz < self.children.len() {
+            Some(tz)
         } else {
             None
         }
@@ -113,13 +189,14 @@ impl Node16 {
     pub(crate) unsafe fn find_index_sse(&self, key: u8) -> Option<usize> {
         use std::arch::x86_64::*;
         let key = _mm_set1_epi8(key as i8);
-        let keys = _mm_load_si128(self.keys.as_slice().as_ptr() as *const _);
+        let keys = _mm_loadu_si128(self.keys.as_slice().as_ptr() as *const _);
         let cmp = _mm_cmpeq_epi8(key, keys);
         let mask = _mm_movemask_epi8(cmp);
-        let tz = mask.trailing_zeros();
+        let tz = mask.trailing_zeros() as usize;
 
-        if tz < 31 {
-            Some(tz as usize)
+        // slots past the last child are zero padded, ignore matches there
+        if tz < self.children.len() {
+            Some(tz)
         } else {
             None
         }
@@ -138,38 +215,30 @@ impl Node16 {
             }
         }
 
-        match self.children.binary_search_by(|x| x.0.cmp(&key)) {
-            Err(E) => None,
-            Ok(index) => Some(index),
-        }
+        self.children.binary_search_by(|x| x.0.cmp(&key)).ok()
     }
-    pub(crate) fn child_at(&self, key: u8) -> Option<&Node> {
-        let index = self.find_index(key);
-        if index.is_none() {
-            return None;
-        }
 
-        match self.children.get(index.unwrap()) {
+    pub(crate) fn child_at(&self, key: u8) -> Option<&Node<V>> {
+        let index = self.find_index(key)?;
+
+        match self.children.get(index) {
             Some(item) => Some(item.1.borrow()),
             None => None,
         }
     }
 
-    pub(crate) fn child_at_mut(&mut self, key: u8) -> Option<&mut Node> {
-        let index = self.find_index(key);
+    pub(crate) fn child_at_mut(&mut self, key: u8) -> Option<&mut Node<V>> {
         // no match found
-        if index.is_none() {
-            return None;
-        }
+        let index = self.find_index(key)?;
 
-        match self.children.get_mut(index.unwrap()) {
+        match self.children.get_mut(index) {
             Some(item) => Some(item.1.borrow_mut()),
             None => None,
         }
     }
 }
 
-impl Display for Node16 {
+impl<V> Display for Node16<V> {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
         write!(
             f,
@@ -195,12 +264,12 @@ impl Display for Node16 {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use xi_rope::compare::ne_idx;
+    use crate::Node4;
 
     #[test]
     fn test_child_at() {
-        let mut node16 = Node16::new();
-        node16.add_child(Node::None, Some(1));
+        let mut node16: Node16<Vec<u8>> = Node16::new();
+        node16.add_child(Node::None, Some(1)).unwrap();
         println!("&node16 = {:#?}", &node16);
         //        for i in 32..100 {
         //            node16.add_child(Node::None, Some(i));
@@ -220,4 +289,19 @@ mod tests {
         //        assert_eq!(res, Some(&Node::None));
         //        println!("&res = {:#?}", &res);
     }
+
+    #[test]
+    fn test_child_at_after_copy_from_node4() {
+        let mut node4: Node4<Vec<u8>> = Node4::new();
+        for key in [0u8, 97, 98, 99].iter() {
+            node4.add_child(Node::None, Some(*key)).unwrap();
+        }
+        let mut node16: Node16<Vec<u8>> = Node16::new();
+        assert_eq!(node16.child_at(0), None);
+        node16.copy(Node::Node4(node4)).unwrap();
+        for key in [0u8, 97, 98, 99].iter() {
+            assert_eq!(node16.child_at(*key), Some(&Node::None));
+        }
+        assert_eq!(node16.child_at(1), None);
+    }
 }
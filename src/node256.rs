@@ -1,20 +1,33 @@
-use crate::{Node, Node256, NodeMeta, MAX_PREFIX};
+use crate::{ArtError, Node, Node256, NodeMeta, MAX_PREFIX};
 use std::fmt::{Display, Error, Formatter};
+use std::cmp::min;
 use std::collections::HashMap;
+use std::mem::replace;
 
-impl Node256 {
+impl<V> Node256<V> {
+    #[cfg(test)]
     pub(crate) fn new() -> Self {
-        Node256 {
+        Self::try_new().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub(crate) fn try_new() -> Result<Self, ArtError> {
+        let mut partial = Vec::new();
+        partial.try_reserve_exact(MAX_PREFIX)?;
+        let mut children = Vec::new();
+        children.try_reserve_exact(256)?;
+        children.resize_with(256, || Node::None);
+        Ok(Node256 {
             meta: NodeMeta {
                 prefix_len: 0,
-                partial: Vec::with_capacity(MAX_PREFIX),
+                partial,
             },
-            children: vec![Node::None; 256],
+            children,
+            count: 0,
             term_leaf: None,
-        }
+        })
     }
 
-    pub(crate) fn copy(&mut self, node_to_copy: Node) {
+    pub(crate) fn copy(&mut self, node_to_copy: Node<V>) -> Result<(), ArtError> {
         match node_to_copy {
             Node::Node48(node) => {
                 self.meta = node.meta;
@@ -31,28 +44,58 @@ impl Node256 {
                 // for (key, child) in node.children.drain(1..).enumerate() {
                 //     self.children[key] = child;
                 // }
-                let mut idx = 0i8;
-                for child in node.children {
-                    let key = map.get(&idx).unwrap();
+                for (idx, child) in node.children.into_iter().enumerate() {
+                    let key = map
+                        .get(&(idx as i8))
+                        .ok_or(ArtError::Corrupted("node48 child without a key"))?;
+                    if !matches!(child, Node::None) {
+                        self.count += 1;
+                    }
                     self.children[*key] = child;
-                    idx += 1;
                 }
+                Ok(())
             }
-            _ => panic!("only copying from node16 is allowed"),
-        };
+            _ => Err(ArtError::Corrupted("only copying from node48 is allowed")),
+        }
+    }
+
+    pub(crate) fn should_shrink(&self) -> bool {
+        self.len() <= 37
     }
 
-    pub(crate) fn add_child(&mut self, node: Node, key_char: Option<u8>) {
+    pub(crate) fn add_child(
+        &mut self,
+        node: Node<V>,
+        key_char: Option<u8>,
+    ) -> Result<(), ArtError> {
         match key_char {
             Some(current_char) => {
                 let current_char = current_char as usize;
-                self.children[current_char] = node;
+                if let Node::None = replace(&mut self.children[current_char], node) {
+                    self.count += 1;
+                }
             }
             None => {
                 // key char would be None in the case of leaf nodes.
                 self.term_leaf = Some(Box::new(node))
             }
         }
+        Ok(())
+    }
+
+    pub(crate) fn remove_child(&mut self, key_char: Option<u8>) -> Option<Node<V>> {
+        match key_char {
+            Some(current_char) => {
+                match replace(&mut self.children[current_char as usize], Node::None) {
+                    Node::None => None,
+                    child => {
+                        self.count -= 1;
+                        Some(child)
+                    }
+                }
+            }
+            None => self.term_leaf.take().map(|leaf| *leaf),
+        }
     }
 
     pub(crate) fn len(&self) -> usize {
@@ -60,29 +103,32 @@ impl Node256 {
         if self.term_leaf.is_some() {
             leaf_count += 1;
         }
-        self.children.len() + leaf_count
+        self.count + leaf_count
     }
 
-    pub(crate) fn first(&self) -> &Node {
-        self.children
-            .iter()
-            .find(|x| match x {
-                Node::None => false,
-                node => true,
-            })
-            .unwrap()
+    pub(crate) fn first(&self) -> Option<&Node<V>> {
+        self.children.iter().find(|x| !matches!(x, Node::None))
+    }
+
+    pub(crate) fn next_child(&self, from: usize) -> Option<(usize, &Node<V>)> {
+        let key = (from..256).find(|key| !matches!(self.children[*key], Node::None))?;
+        Some((key, &self.children[key]))
     }
 
-    pub(crate) fn children(&self) -> Vec<(Option<u8>, &Node)> {
-        let mut res: Vec<(Option<u8>, &Node)> = self
+    pub(crate) fn prev_child(&self, before: usize) -> Option<(usize, &Node<V>)> {
+        let key = (0..min(before, 256))
+            .rev()
+            .find(|key| !matches!(self.children[*key], Node::None))?;
+        Some((key, &self.children[key]))
+    }
+
+    pub(crate) fn children(&self) -> Vec<(Option<u8>, &Node<V>)> {
+        let mut res: Vec<(Option<u8>, &Node<V>)> = self
             .children
             .iter()
             .enumerate()
             .map(|n| (Some(n.0 as u8), n.1))
-            .filter(|n| match n.1 {
-                Node::None => false,
-                _ => true,
-            })
+            .filter(|n| !matches!(n.1, Node::None))
             .collect();
         if self.term_leaf().is_some() {
             res.push((None, self.term_leaf.as_ref().unwrap()));
@@ -95,20 +141,17 @@ impl Node256 {
             .iter()
             .enumerate()
             .map(|n| (n.0 as u8, n.1))
-            .filter(|n| match n.1 {
-                Node::None => false,
-                _ => true,
-            })
+            .filter(|n| !matches!(n.1, Node::None))
             .map(|n| n.0)
             .collect()
     }
 
-    pub(crate) fn term_leaf_mut(&mut self) -> Option<&mut Box<Node>> {
+    pub(crate) fn term_leaf_mut(&mut self) -> Option<&mut Box<Node<V>>> {
         self.term_leaf.as_mut()
     }
 
-    pub(crate) fn term_leaf(&self) -> Option<&Box<Node>> {
-        self.term_leaf.as_ref()
+    pub(crate) fn term_leaf(&self) -> Option<&Node<V>> {
+        self.term_leaf.as_deref()
     }
 
     pub(crate) fn partial(&self) -> &[u8] {
@@ -119,26 +162,24 @@ impl Node256 {
         self.meta.prefix_len
     }
 
-    pub(crate) fn child_at(&self, key: u8) -> Option<&Node> {
-        let res = self.children.get(key as usize).unwrap();
-        let res = match res {
+    pub(crate) fn child_at(&self, key: u8) -> Option<&Node<V>> {
+        let res = self.children.get(key as usize)?;
+        match res {
             Node::None => None,
             _ => Some(res),
-        };
-        res
+        }
     }
 
-    pub(crate) fn child_at_mut(&mut self, key: u8) -> Option<&mut Node> {
-        let res = self.children.get_mut(key as usize).unwrap();
-        let res = match res {
+    pub(crate) fn child_at_mut(&mut self, key: u8) -> Option<&mut Node<V>> {
+        let res = self.children.get_mut(key as usize)?;
+        match res {
             Node::None => None,
             _ => Some(res),
-        };
-        res
+        }
     }
 }
 
-impl Display for Node256 {
+impl<V> Display for Node256<V> {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
         write!(
             f,
@@ -160,3 +201,43 @@ impl Display for Node256 {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Leaf, Node4};
+
+    #[test]
+    fn test_copy_from_node48_only() {
+        let mut node256: Node256<Vec<u8>> = Node256::new();
+        let err = node256.copy(Node::Node4(Node4::new())).unwrap_err();
+        assert_eq!(
+            err,
+            ArtError::Corrupted("only copying from node48 is allowed")
+        );
+        assert!(node256.keys().is_empty());
+    }
+
+    #[test]
+    fn test_len_follows_children() {
+        let mut node256: Node256<Vec<u8>> = Node256::new();
+        for key_char in 0..=255u8 {
+            let leaf = Node::Leaf(Leaf::new(vec![key_char], vec![key_char]));
+            node256.add_child(leaf, Some(key_char)).unwrap();
+        }
+        let term_leaf = Node::Leaf(Leaf::new(Vec::new(), Vec::new()));
+        node256.add_child(term_leaf, None).unwrap();
+        assert_eq!(node256.len(), 257);
+
+        // replacing a child or removing a missing one leaves the count alone
+        let leaf = Node::Leaf(Leaf::new(vec![7], Vec::new()));
+        node256.add_child(leaf, Some(7)).unwrap();
+        assert_eq!(node256.len(), 257);
+        assert!(node256.remove_child(Some(7)).is_some());
+        assert!(node256.remove_child(Some(7)).is_none());
+        assert_eq!(node256.len(), 256);
+        assert!(node256.remove_child(None).is_some());
+        assert_eq!(node256.len(), 255);
+        assert!(!node256.should_shrink());
+    }
+}
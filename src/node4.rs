@@ -1,21 +1,37 @@
-use crate::{Leaf, Node, Node16, Node4, NodeMeta, MAX_PREFIX};
+use crate::{ArtError, Node, Node4, NodeMeta, MAX_PREFIX};
 use std::borrow::{Borrow, BorrowMut};
+use std::cmp::min;
 use std::fmt::{Display, Error, Formatter};
-use std::mem::replace;
 
-impl Node4 {
+impl<V> Node4<V> {
     pub(crate) fn should_grow(&self) -> bool {
         self.keys().len() == 4
     }
 
-    pub(crate) fn child_at(&self, key: u8) -> Option<&Node> {
+    pub(crate) fn should_collapse(&self) -> bool {
+        self.len() == 1
+    }
+
+    pub(crate) fn copy(&mut self, node_to_copy: Node<V>) -> Result<(), ArtError> {
+        match node_to_copy {
+            Node::Node16(node16) => {
+                self.meta = node16.meta;
+                self.children = node16.children;
+                self.term_leaf = node16.term_leaf;
+                Ok(())
+            }
+            _ => Err(ArtError::Corrupted("only copying from node16 is allowed")),
+        }
+    }
+
+    pub(crate) fn child_at(&self, key: u8) -> Option<&Node<V>> {
         match self.children.iter().find(|n| n.0 == key) {
             Some(item) => Some(item.1.borrow()),
             None => None,
         }
     }
 
-    pub(crate) fn child_at_mut(&mut self, key: u8) -> Option<&mut Node> {
+    pub(crate) fn child_at_mut(&mut self, key: u8) -> Option<&mut Node<V>> {
         match self.children.iter_mut().find(|n| n.0 == key) {
             Some(item) => Some(item.1.borrow_mut()),
             None => None,
@@ -30,12 +46,28 @@ impl Node4 {
         self.children.len() + leaf_count
     }
 
-    pub(crate) fn first(&self) -> &Node {
-        self.children.first().unwrap().1.borrow()
+    pub(crate) fn first(&self) -> Option<&Node<V>> {
+        self.children.first().map(|child| child.1.borrow())
+    }
+
+    pub(crate) fn next_child(&self, from: usize) -> Option<(usize, &Node<V>)> {
+        self.children.get(from).map(|child| (from, &child.1))
+    }
+
+    pub(crate) fn prev_child(&self, before: usize) -> Option<(usize, &Node<V>)> {
+        let index = min(before, self.children.len()).checked_sub(1)?;
+        Some((index, &self.children[index].1))
     }
 
-    pub(crate) fn children(&self) -> Vec<(Option<u8>, &Node)> {
-        let mut res: Vec<(Option<u8>, &Node)> =
+    pub(crate) fn seek_child(&self, key: u8) -> (usize, Option<&Node<V>>) {
+        match self.children.binary_search_by_key(&key, |child| child.0) {
+            Ok(index) => (index, Some(&self.children[index].1)),
+            Err(index) => (index, None),
+        }
+    }
+
+    pub(crate) fn children(&self) -> Vec<(Option<u8>, &Node<V>)> {
+        let mut res: Vec<(Option<u8>, &Node<V>)> =
             self.children.iter().map(|n| (Some(n.0), &n.1)).collect();
         if self.term_leaf().is_some() {
             res.push((None, self.term_leaf.as_ref().unwrap()));
@@ -47,26 +79,36 @@ impl Node4 {
         self.children.iter().map(|i| i.0).collect()
     }
 
-    pub(crate) fn outgoing_children(&self) -> Vec<&Node> {
+    #[cfg(test)]
+    pub(crate) fn outgoing_children(&self) -> Vec<&Node<V>> {
         self.children.iter().map(|i| i.1.borrow()).collect()
     }
 
-    pub(crate) fn term_leaf_mut(&mut self) -> Option<&mut Box<Node>> {
+    pub(crate) fn term_leaf_mut(&mut self) -> Option<&mut Box<Node<V>>> {
         self.term_leaf.as_mut()
     }
-    pub(crate) fn term_leaf(&self) -> Option<&Box<Node>> {
-        self.term_leaf.as_ref()
+    pub(crate) fn term_leaf(&self) -> Option<&Node<V>> {
+        self.term_leaf.as_deref()
     }
 
+    #[cfg(test)]
     pub(crate) fn new() -> Self {
-        Node4 {
+        Self::try_new().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub(crate) fn try_new() -> Result<Self, ArtError> {
+        let mut partial = Vec::new();
+        partial.try_reserve_exact(MAX_PREFIX)?;
+        let mut children = Vec::new();
+        children.try_reserve_exact(4)?;
+        Ok(Node4 {
             meta: NodeMeta {
                 prefix_len: 0,
-                partial: Vec::with_capacity(MAX_PREFIX),
+                partial,
             },
-            children: Vec::with_capacity(4),
+            children,
             term_leaf: None,
-        }
+        })
     }
 
     pub(crate) fn partial(&self) -> &[u8] {
@@ -77,21 +119,49 @@ impl Node4 {
         self.meta.prefix_len
     }
 
-    pub(crate) fn add_child(&mut self, node: Node, key_char: Option<u8>) {
+    pub(crate) fn add_child(
+        &mut self,
+        node: Node<V>,
+        key_char: Option<u8>,
+    ) -> Result<(), ArtError> {
         match key_char {
             Some(current_char) => {
+                self.children.try_reserve(1)?;
                 self.children.push((current_char, node));
-                self.children.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+                self.children.sort_unstable_by_key(|a| a.0);
             }
             None => {
                 // key char would be None in the case of leaf nodes.
                 self.term_leaf = Some(Box::new(node))
             }
         }
+        Ok(())
+    }
+
+    pub(crate) fn remove_child(&mut self, key_char: Option<u8>) -> Option<Node<V>> {
+        match key_char {
+            Some(current_char) => {
+                let index = self.children.iter().position(|n| n.0 == current_char)?;
+                Some(self.children.remove(index).1)
+            }
+            None => self.term_leaf.take().map(|leaf| *leaf),
+        }
+    }
+
+    // returns the only remaining entry along with the byte it hangs off, used to merge a
+    // single child node4 into its parent.
+    pub(crate) fn take_only_child(&mut self) -> Option<(Option<u8>, Node<V>)> {
+        match self.term_leaf.take() {
+            Some(leaf) => Some((None, *leaf)),
+            None => {
+                let (key_char, child) = self.children.pop()?;
+                Some((Some(key_char), child))
+            }
+        }
     }
 }
 
-impl Display for Node4 {
+impl<V> Display for Node4<V> {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
         write!(
             f,
@@ -117,20 +187,20 @@ impl Display for Node4 {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::Leaf;
+    use crate::{Leaf, Node16};
 
     #[test]
     fn test_add_child4() {
         let mut node4 = Node4::new();
         // add first child
-        node4.add_child(Node::None, Some(1));
-        node4.add_child(Node::None, Some(4));
-        node4.add_child(Node::None, Some(2));
-        node4.add_child(Node::None, Some(3));
+        node4.add_child(Node::None, Some(1)).unwrap();
+        node4.add_child(Node::None, Some(4)).unwrap();
+        node4.add_child(Node::None, Some(2)).unwrap();
+        node4.add_child(Node::None, Some(3)).unwrap();
 
         let keys: Vec<u8> = (1..5).collect();
         let nodes = vec![Node::None; 4];
-        let res: Vec<(Option<u8>, &Node)> = keys
+        let res: Vec<(Option<u8>, &Node<Vec<u8>>)> = keys
             .iter()
             .zip(nodes.iter())
             .map(|x| (Some(*x.0), x.1))
@@ -143,14 +213,14 @@ mod tests {
         let mut node4 = Node4::new();
         println!("&node4 = {:#?}", &node4);
         // add first child
-        node4.add_child(Node::None, Some(1));
+        node4.add_child(Node::None, Some(1)).unwrap();
         println!("&node4 = {:#?}", &node4);
         // leaf
         let k = "1".as_bytes().to_vec();
         let leaf = Node::Leaf(Leaf::new(k.clone(), k));
-        node4.add_child(leaf.clone(), None);
+        node4.add_child(leaf.clone(), None).unwrap();
         // another child
-        node4.add_child(Node::None, Some(4));
+        node4.add_child(Node::None, Some(4)).unwrap();
 
         let keys: Vec<u8> = vec![1, 4];
         let nodes = vec![&Node::None; 2];
@@ -160,13 +230,14 @@ mod tests {
 
     #[test]
     fn test_vec_sorting_by_node() {
-        let mut items = Vec::new();
-        items.push((1, Node::None));
-        items.push((3, Node::None));
-        items.push((2, Node::None));
-        items.push((4, Node::None));
+        let mut items = vec![
+            (1, Node::<Vec<u8>>::None),
+            (3, Node::None),
+            (2, Node::None),
+            (4, Node::None),
+        ];
 
-        items.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+        items.sort_unstable_by_key(|a| a.0);
 
         println!("&items = {:#?}", &items);
     }
@@ -174,11 +245,11 @@ mod tests {
     #[test]
     fn test_display_string() {
         let mut node4 = Node4::new();
-        node4.add_child(Node::None, Some(1));
-        node4.add_child(Node::None, Some(4));
-        node4.add_child(Node::None, Some(2));
-        node4.add_child(Node::None, Some(3));
-        let chars = vec![1, 2, 3, 4]
+        node4.add_child(Node::None, Some(1)).unwrap();
+        node4.add_child(Node::None, Some(4)).unwrap();
+        node4.add_child(Node::None, Some(2)).unwrap();
+        node4.add_child(Node::None, Some(3)).unwrap();
+        let chars = [1, 2, 3, 4]
             .iter()
             .map(|i| *i as u8 as char)
             .collect::<Vec<char>>();
@@ -191,7 +262,7 @@ mod tests {
 
         let k = "1".as_bytes().to_vec();
         let leaf = Node::Leaf(Leaf::new(k.clone(), k));
-        node4.add_child(leaf.clone(), None);
+        node4.add_child(leaf.clone(), None).unwrap();
         let match_str = format!(
             "Node4(5) [1, 2, 3, 4] {chars:?} (true) - (0) [[]]",
             chars = chars
@@ -199,4 +270,33 @@ mod tests {
         let node_str = format!("{}", &node4);
         assert_eq!(match_str, node_str);
     }
+
+    #[test]
+    fn test_remove_child() {
+        let mut node4 = Node4::new();
+        node4.add_child(Node::None, Some(1)).unwrap();
+        node4.add_child(Node::None, Some(2)).unwrap();
+        let k = "1".as_bytes().to_vec();
+        let leaf = Node::Leaf(Leaf::new(k.clone(), k));
+        node4.add_child(leaf.clone(), None).unwrap();
+
+        assert_eq!(node4.remove_child(Some(3)), None);
+        assert_eq!(node4.remove_child(Some(1)), Some(Node::None));
+        assert_eq!(node4.keys(), vec![2]);
+        assert_eq!(node4.remove_child(None), Some(leaf));
+        assert!(node4.term_leaf().is_none());
+        assert!(node4.should_collapse());
+        assert_eq!(node4.take_only_child(), Some((Some(2), Node::None)));
+    }
+
+    #[test]
+    fn test_copy_from_node16() {
+        let mut node16: Node16<Vec<u8>> = Node16::new();
+        for i in (1..4).rev() {
+            node16.add_child(Node::None, Some(i)).unwrap();
+        }
+        let mut node4 = Node4::new();
+        node4.copy(Node::Node16(node16)).unwrap();
+        assert_eq!(node4.keys(), vec![1, 2, 3]);
+    }
 }
@@ -1,33 +1,61 @@
-use crate::{Node, Node48, NodeMeta, MAX_PREFIX};
-use std::borrow::{Borrow, BorrowMut};
+use crate::{ArtError, Node, Node48, NodeMeta, MAX_PREFIX};
+use std::borrow::BorrowMut;
+use std::cmp::min;
 use std::fmt::{Display, Error, Formatter};
-use std::mem::replace;
 
-impl Node48 {
+impl<V> Node48<V> {
+    #[cfg(test)]
     pub(crate) fn new() -> Self {
-        Node48 {
+        Self::try_new().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    pub(crate) fn try_new() -> Result<Self, ArtError> {
+        let mut partial = Vec::new();
+        partial.try_reserve_exact(MAX_PREFIX)?;
+        let mut keys = Vec::new();
+        keys.try_reserve_exact(256)?;
+        keys.resize(256, -1);
+        let mut children = Vec::new();
+        children.try_reserve_exact(48)?;
+        Ok(Node48 {
             meta: NodeMeta {
                 prefix_len: 0,
-                partial: Vec::with_capacity(MAX_PREFIX),
+                partial,
             },
-            keys: vec![-1; 256],
-            children: Vec::with_capacity(48),
+            keys,
+            children,
             term_leaf: None,
-        }
+        })
     }
 
-    pub(crate) fn copy(&mut self, node_to_copy: Node) {
+    pub(crate) fn copy(&mut self, node_to_copy: Node<V>) -> Result<(), ArtError> {
         match node_to_copy {
             Node::Node16(node16) => {
-                replace(&mut self.meta, node16.meta);
-                replace(&mut self.term_leaf, node16.term_leaf);
+                self.meta = node16.meta;
+                self.term_leaf = node16.term_leaf;
 
                 for child in node16.children {
                     self.children.push(child.1);
                     self.keys[child.0 as usize] = (self.children.len() - 1) as i8;
                 }
+                Ok(())
+            }
+            Node::Node256(node256) => {
+                self.meta = node256.meta;
+                self.term_leaf = node256.term_leaf;
+
+                for (key, child) in node256.children.into_iter().enumerate() {
+                    if let Node::None = child {
+                        continue;
+                    }
+                    self.children.push(child);
+                    self.keys[key] = (self.children.len() - 1) as i8;
+                }
+                Ok(())
             }
-            _ => panic!("only copying from node16 is allowed"),
+            _ => Err(ArtError::Corrupted(
+                "only copying from node16 or node256 is allowed",
+            )),
         }
     }
 
@@ -35,9 +63,18 @@ impl Node48 {
         self.children.len() == 48
     }
 
-    pub(crate) fn add_child(&mut self, node: Node, key_char: Option<u8>) {
+    pub(crate) fn should_shrink(&self) -> bool {
+        self.len() <= 12
+    }
+
+    pub(crate) fn add_child(
+        &mut self,
+        node: Node<V>,
+        key_char: Option<u8>,
+    ) -> Result<(), ArtError> {
         match key_char {
             Some(current_char) => {
+                self.children.try_reserve(1)?;
                 self.children.push(node);
                 self.keys[current_char as usize] = (self.children.len() - 1) as i8;
             }
@@ -46,6 +83,30 @@ impl Node48 {
                 self.term_leaf = Some(Box::new(node))
             }
         }
+        Ok(())
+    }
+
+    pub(crate) fn remove_child(&mut self, key_char: Option<u8>) -> Option<Node<V>> {
+        match key_char {
+            Some(current_char) => {
+                let key_index = self.keys[current_char as usize];
+                if key_index < 0 {
+                    return None;
+                }
+                self.keys[current_char as usize] = -1;
+
+                // the last child moves into the freed slot, repoint the key that referenced it
+                let last_index = (self.children.len() - 1) as i8;
+                let child = self.children.swap_remove(key_index as usize);
+                if key_index != last_index {
+                    if let Some(moved) = self.keys.iter_mut().find(|k| **k == last_index) {
+                        *moved = key_index;
+                    }
+                }
+                Some(child)
+            }
+            None => self.term_leaf.take().map(|leaf| *leaf),
+        }
     }
 
     pub(crate) fn len(&self) -> usize {
@@ -56,23 +117,27 @@ impl Node48 {
         self.children.len() + leaf_count
     }
 
-    pub(crate) fn first(&self) -> &Node {
-        let mut key_index = 0;
-        for key in self.keys.iter().enumerate() {
-            if *key.1 >= 0 {
-                key_index = key.0;
-                break;
-            }
-        }
-        self.children.get(key_index).unwrap().borrow()
+    pub(crate) fn first(&self) -> Option<&Node<V>> {
+        let key_index = self.keys.iter().find(|key_index| **key_index >= 0)?;
+        self.children.get(*key_index as usize)
     }
 
-    pub(crate) fn children(&self) -> Vec<(Option<u8>, &Node)> {
-        let mut result: Vec<(Option<u8>, &Node)> = Vec::new();
+    pub(crate) fn next_child(&self, from: usize) -> Option<(usize, &Node<V>)> {
+        let key = (from..256).find(|key| self.keys[*key] >= 0)?;
+        Some((key, &self.children[self.keys[key] as usize]))
+    }
+
+    pub(crate) fn prev_child(&self, before: usize) -> Option<(usize, &Node<V>)> {
+        let key = (0..min(before, 256)).rev().find(|key| self.keys[*key] >= 0)?;
+        Some((key, &self.children[self.keys[key] as usize]))
+    }
+
+    pub(crate) fn children(&self) -> Vec<(Option<u8>, &Node<V>)> {
+        let mut result: Vec<(Option<u8>, &Node<V>)> = Vec::new();
         for key in self.keys.iter().enumerate() {
             if *key.1 >= 0 {
                 let key_index = *key.1;
-                result.push((Some(key.0 as u8), self.children.get(key_index as usize).unwrap().borrow()))
+                result.push((Some(key.0 as u8), &self.children[key_index as usize]))
             }
         }
         if self.term_leaf().is_some() {
@@ -85,12 +150,12 @@ impl Node48 {
         self.keys.iter().enumerate().filter(|x| *x.1 >= 0).map(|x| x.0 as u8).collect()
     }
 
-    pub(crate) fn term_leaf_mut(&mut self) -> Option<&mut Box<Node>> {
+    pub(crate) fn term_leaf_mut(&mut self) -> Option<&mut Box<Node<V>>> {
         self.term_leaf.as_mut()
     }
 
-    pub(crate) fn term_leaf(&self) -> Option<&Box<Node>> {
-        self.term_leaf.as_ref()
+    pub(crate) fn term_leaf(&self) -> Option<&Node<V>> {
+        self.term_leaf.as_deref()
     }
 
     pub(crate) fn partial(&self) -> &[u8] {
@@ -102,19 +167,16 @@ impl Node48 {
     }
 
 
-    pub(crate) fn child_at(&self, key: u8) -> Option<&Node> {
+    pub(crate) fn child_at(&self, key: u8) -> Option<&Node<V>> {
         let key_index = self.keys[key as usize];
         if key_index < 0 {
             return None
         }
 
-        match self.children.get(key_index as usize) {
-            Some(item) => Some(item.borrow()),
-            None => None,
-        }
+        self.children.get(key_index as usize)
     }
 
-    pub(crate) fn child_at_mut(&mut self, key: u8) -> Option<&mut Node> {
+    pub(crate) fn child_at_mut(&mut self, key: u8) -> Option<&mut Node<V>> {
         let key_index = self.keys[key as usize];
         if key_index < 0 {
             return None
@@ -127,7 +189,7 @@ impl Node48 {
     }
 }
 
-impl Display for Node48 {
+impl<V> Display for Node48<V> {
     fn fmt(&self, f: &mut Formatter) -> Result<(), Error> {
         write!(
             f,
@@ -156,10 +218,10 @@ mod tests {
 
     #[test]
     fn test_child_at() {
-        let mut node48 = Node48::new();
-        node48.add_child(Node::None, Some(66));
-        node48.add_child(Node::None, Some(67));
-        node48.add_child(Node::None, Some(75));
+        let mut node48: Node48<Vec<u8>> = Node48::new();
+        node48.add_child(Node::None, Some(66)).unwrap();
+        node48.add_child(Node::None, Some(67)).unwrap();
+        node48.add_child(Node::None, Some(75)).unwrap();
 
         let res = node48.child_at(66);
         assert_eq!(res, Some(&Node::None));
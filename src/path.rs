@@ -0,0 +1,300 @@
+use crate::{Leaf, Node, Path};
+use std::cmp::{min, Ordering};
+
+// A handle on a node that a Path can be walked with, shared references for the iterators
+// and raw pointers for the mutable cursor, which has to modify the nodes on its path.
+pub(crate) trait NodeRef<'a>: Copy {
+    type Value: 'a;
+
+    fn node(self) -> &'a Node<Self::Value>;
+
+    fn next_entry(self, from: usize) -> Option<(usize, Self)>;
+
+    fn prev_entry(self, before: usize) -> Option<(usize, Self)>;
+
+    fn seek_child(self, key_char: u8) -> (usize, Option<Self>);
+}
+
+impl<'a, V> NodeRef<'a> for &'a Node<V> {
+    type Value = V;
+
+    fn node(self) -> &'a Node<V> {
+        self
+    }
+
+    fn next_entry(self, from: usize) -> Option<(usize, Self)> {
+        Node::next_entry(self, from)
+    }
+
+    fn prev_entry(self, before: usize) -> Option<(usize, Self)> {
+        Node::prev_entry(self, before)
+    }
+
+    fn seek_child(self, key_char: u8) -> (usize, Option<Self>) {
+        Node::seek_child(self, key_char)
+    }
+}
+
+impl<'a, P: NodeRef<'a>> Path<P> {
+    pub(crate) fn new() -> Self {
+        Path {
+            stack: Vec::new(),
+            leaf: None,
+        }
+    }
+
+    pub(crate) fn leaf(&self) -> Option<&'a Leaf<P::Value>> {
+        match self.leaf?.node() {
+            Node::Leaf(leaf) => Some(leaf),
+            _ => None,
+        }
+    }
+
+    // the node holding the current leaf
+    pub(crate) fn leaf_ref(&self) -> Option<P> {
+        self.leaf
+    }
+
+    // the innermost node on the path and the depth its compressed prefix starts at
+    pub(crate) fn pop(&mut self) -> Option<(P, usize)> {
+        let (node, _) = self.stack.pop()?;
+        let depth = self
+            .stack
+            .iter()
+            .map(|(node, _)| node.node().prefix_len() + 1)
+            .sum();
+        Some((node, depth))
+    }
+
+    // seek within the subtree of a node that was popped off the path, falling back to its
+    // ancestors that are still on the stack when nothing in it qualifies
+    pub(crate) fn seek_from(&mut self, node: P, depth: usize, key: &[u8], inclusive: bool) {
+        self.leaf = self.seek_forward(node, depth, key, inclusive);
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.stack.clear();
+        self.leaf = None;
+    }
+
+    pub(crate) fn first(&mut self, root: P) {
+        self.stack.clear();
+        self.leaf = self.descend_first(root);
+    }
+
+    pub(crate) fn last(&mut self, root: P) {
+        self.stack.clear();
+        self.leaf = self.descend_last(root);
+    }
+
+    pub(crate) fn next(&mut self) {
+        self.leaf = self.advance();
+    }
+
+    pub(crate) fn prev(&mut self) {
+        self.leaf = self.retreat();
+    }
+
+    // position at the first key that is >= key, or > key when not inclusive
+    pub(crate) fn seek(&mut self, root: P, key: &[u8], inclusive: bool) {
+        self.stack.clear();
+        self.leaf = self.seek_forward(root, 0, key, inclusive);
+    }
+
+    // position at the last key that is <= key, or < key when not inclusive
+    pub(crate) fn seek_back(&mut self, root: P, key: &[u8], inclusive: bool) {
+        self.stack.clear();
+        self.leaf = self.seek_backward(root, key, inclusive);
+    }
+
+    // move on to the first key that is >= key, or > key when not inclusive, where key is after
+    // the current leaf. Only the innermost node on the path whose subtree can hold the key is
+    // searched again, every subtree between the current leaf and the key is passed over whole.
+    pub(crate) fn seek_ahead(&mut self, root: P, key: &[u8], inclusive: bool) {
+        let current = match self.leaf() {
+            Some(leaf) => leaf.key.as_slice(),
+            None => return,
+        };
+        while let Some((node, depth)) = self.pop() {
+            // the subtree holds the keys starting with the path down to the node, which the
+            // current key starts with too
+            let end = depth + node.node().prefix_len();
+            if key.len() >= end && key[..end] == current[..end] {
+                self.seek_from(node, depth, key, inclusive);
+                return;
+            }
+        }
+        self.seek(root, key, inclusive);
+    }
+
+    fn seek_forward(&mut self, node: P, depth: usize, key: &[u8], inclusive: bool) -> Option<P> {
+        let mut current = node;
+        let mut depth = depth;
+        loop {
+            match current.node() {
+                Node::None => return self.advance(),
+                Node::Leaf(leaf) => {
+                    return match leaf.key.as_slice().cmp(key) {
+                        Ordering::Greater => Some(current),
+                        Ordering::Equal if inclusive => Some(current),
+                        _ => self.advance(),
+                    };
+                }
+                inner => {
+                    // compare the compressed path against the key, if they differ the whole
+                    // subtree is either before or after the key
+                    let prefix = inner.full_prefix(depth);
+                    let key_part =
+                        &key[min(depth, key.len())..min(depth + prefix.len(), key.len())];
+                    match prefix[..key_part.len()].cmp(key_part) {
+                        Ordering::Less => return self.advance(),
+                        Ordering::Greater => return self.descend_first(current),
+                        Ordering::Equal => {}
+                    }
+                    // the key ends inside the path, everything below is longer
+                    if key_part.len() < prefix.len() {
+                        return self.descend_first(current);
+                    }
+                    depth += prefix.len();
+
+                    // the key ends at this node, only the term leaf can be equal to it
+                    if depth == key.len() {
+                        if inclusive {
+                            return self.descend_first(current);
+                        }
+                        self.stack.push((current, 0));
+                        return self.advance();
+                    }
+
+                    let (position, child) = current.seek_child(key[depth]);
+                    match child {
+                        Some(child) => {
+                            self.stack.push((current, position));
+                            current = child;
+                            depth += 1;
+                        }
+                        None => {
+                            // resume from the first child after the missing key byte
+                            self.stack.push((current, position - 1));
+                            return self.advance();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn seek_backward(&mut self, root: P, key: &[u8], inclusive: bool) -> Option<P> {
+        let mut current = root;
+        let mut depth = 0;
+        loop {
+            match current.node() {
+                Node::None => return None,
+                Node::Leaf(leaf) => {
+                    return match leaf.key.as_slice().cmp(key) {
+                        Ordering::Less => Some(current),
+                        Ordering::Equal if inclusive => Some(current),
+                        _ => self.retreat(),
+                    };
+                }
+                inner => {
+                    let prefix = inner.full_prefix(depth);
+                    let key_part =
+                        &key[min(depth, key.len())..min(depth + prefix.len(), key.len())];
+                    match prefix[..key_part.len()].cmp(key_part) {
+                        Ordering::Less => return self.descend_last(current),
+                        Ordering::Greater => return self.retreat(),
+                        Ordering::Equal => {}
+                    }
+                    // the key ends inside the path, everything below is after it
+                    if key_part.len() < prefix.len() {
+                        return self.retreat();
+                    }
+                    depth += prefix.len();
+
+                    // the key ends at this node, all the children are after it
+                    if depth == key.len() {
+                        self.stack.push((current, 0));
+                        if inclusive && inner.term_leaf().is_some() {
+                            return current.next_entry(0).map(|(_, term_leaf)| term_leaf);
+                        }
+                        return self.retreat();
+                    }
+
+                    let (position, child) = current.seek_child(key[depth]);
+                    self.stack.push((current, position));
+                    match child {
+                        Some(child) => {
+                            current = child;
+                            depth += 1;
+                        }
+                        // resume from the last entry before the missing key byte
+                        None => return self.retreat(),
+                    }
+                }
+            }
+        }
+    }
+
+    // walk down the smallest entries until a leaf is reached, remembering the path
+    fn descend_first(&mut self, node: P) -> Option<P> {
+        let mut current = node;
+        loop {
+            match current.node() {
+                Node::None => return None,
+                Node::Leaf(_) => return Some(current),
+                _ => {
+                    let (position, child) = current.next_entry(0)?;
+                    self.stack.push((current, position));
+                    current = child;
+                }
+            }
+        }
+    }
+
+    fn descend_last(&mut self, node: P) -> Option<P> {
+        let mut current = node;
+        loop {
+            match current.node() {
+                Node::None => return None,
+                Node::Leaf(_) => return Some(current),
+                _ => {
+                    let (position, child) = current.prev_entry(usize::MAX)?;
+                    self.stack.push((current, position));
+                    current = child;
+                }
+            }
+        }
+    }
+
+    // move on to the entry following the current leaf, popping exhausted nodes
+    fn advance(&mut self) -> Option<P> {
+        loop {
+            let (node, position) = self.stack.last_mut()?;
+            match node.next_entry(*position + 1) {
+                Some((next_position, child)) => {
+                    *position = next_position;
+                    return self.descend_first(child);
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+
+    fn retreat(&mut self) -> Option<P> {
+        loop {
+            let (node, position) = self.stack.last_mut()?;
+            match node.prev_entry(*position) {
+                Some((prev_position, child)) => {
+                    *position = prev_position;
+                    return self.descend_last(child);
+                }
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,416 @@
+use crate::{Art, ArtKey, Cidr, CoveringRoutes, IpRoutingTable, Prefixes, Routes};
+use std::borrow::Cow;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+// the first byte of a key, keeping the IPv4 networks apart from the IPv6 ones
+const V4: u8 = 4;
+const V6: u8 = 6;
+
+impl Cidr {
+    /// Returns the network made of the first `prefix_len` bits of `addr`, or `None` if the
+    /// address doesn't have that many bits.
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Option<Self> {
+        let (family, bits) = Self::bits(addr);
+        if prefix_len > Self::width(family) {
+            return None;
+        }
+        Some(Self::from_bits(
+            family,
+            bits & Self::mask(prefix_len),
+            prefix_len,
+        ))
+    }
+
+    /// Returns the network holding `addr` alone.
+    pub fn host(addr: IpAddr) -> Self {
+        let (family, bits) = Self::bits(addr);
+        Self::from_bits(family, bits, Self::width(family))
+    }
+
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    /// Returns `true` if `addr` is in the network.
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        Cidr::new(addr, self.prefix_len) == Some(*self)
+    }
+
+    /// Returns `true` if `other` is the network itself or one of the networks inside it.
+    pub fn covers(&self, other: &Cidr) -> bool {
+        other.prefix_len >= self.prefix_len && self.contains(other.addr)
+    }
+
+    // the address bits aligned to the top of a u128, so both families shift the same way
+    fn bits(addr: IpAddr) -> (u8, u128) {
+        match addr {
+            IpAddr::V4(addr) => (V4, (u32::from(addr) as u128) << 96),
+            IpAddr::V6(addr) => (V6, u128::from(addr)),
+        }
+    }
+
+    fn from_bits(family: u8, bits: u128, prefix_len: u8) -> Self {
+        let addr = if family == V4 {
+            IpAddr::V4(Ipv4Addr::from((bits >> 96) as u32))
+        } else {
+            IpAddr::V6(Ipv6Addr::from(bits))
+        };
+        Cidr { addr, prefix_len }
+    }
+
+    fn width(family: u8) -> u8 {
+        if family == V4 {
+            32
+        } else {
+            128
+        }
+    }
+
+    fn mask(prefix_len: u8) -> u128 {
+        match prefix_len {
+            0 => 0,
+            len => !0 << (128 - len as u32),
+        }
+    }
+
+    // appends a byte per bit of the prefix, the bit plus offset
+    fn encode_bits(&self, buf: &mut Vec<u8>, offset: u8) {
+        let (family, bits) = Self::bits(self.addr);
+        buf.push(family);
+        buf.extend((0..self.prefix_len).map(|idx| ((bits >> (127 - idx)) & 1) as u8 + offset));
+    }
+
+    // the inverse of encode_bits for the bytes after the family
+    fn decode_bits(family: u8, encoded: &[u8], offset: u8) -> Option<Self> {
+        if (family != V4 && family != V6) || encoded.len() > Self::width(family) as usize {
+            return None;
+        }
+        let mut bits = 0u128;
+        for (idx, byte) in encoded.iter().enumerate() {
+            match byte.checked_sub(offset) {
+                Some(0) => {}
+                Some(1) => bits |= 1 << (127 - idx),
+                _ => return None,
+            }
+        }
+        Some(Self::from_bits(family, bits, encoded.len() as u8))
+    }
+}
+
+impl Display for Cidr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+// nested, the bits are stored as 1 and 2 and end with a 0, so that a network still sorts
+// before the networks inside it
+impl ArtKey for Cidr {
+    fn encode_into(&self, buf: &mut Vec<u8>) {
+        self.encode_bits(buf, 0);
+    }
+
+    fn encode_nested(&self, buf: &mut Vec<u8>) {
+        self.encode_bits(buf, 1);
+        buf.push(0);
+    }
+
+    fn decode(bytes: &[u8]) -> Option<Self> {
+        let (family, encoded) = bytes.split_first()?;
+        Self::decode_bits(*family, encoded, 0)
+    }
+
+    fn decode_nested(bytes: &mut &[u8]) -> Option<Self> {
+        let (family, rest) = bytes.split_first()?;
+        let end = rest.iter().position(|byte| *byte == 0)?;
+        let cidr = Self::decode_bits(*family, &rest[..end], 1)?;
+        *bytes = &rest[end + 1..];
+        Some(cidr)
+    }
+}
+
+impl<V> Default for IpRoutingTable<V> {
+    fn default() -> Self {
+        IpRoutingTable::new()
+    }
+}
+
+impl<V: Debug> Debug for IpRoutingTable<V> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<V> IpRoutingTable<V> {
+    pub fn new() -> Self {
+        IpRoutingTable { map: Art::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Adds a route for `cidr`, returning the value of the route it replaces.
+    pub fn insert(&mut self, cidr: Cidr, value: V) -> Option<V> {
+        self.map.insert(cidr, value)
+    }
+
+    /// Removes the route for exactly `cidr`, leaving the routes for the networks around and
+    /// inside it in place.
+    pub fn remove(&mut self, cidr: &Cidr) -> Option<V> {
+        self.map.remove(cidr)
+    }
+
+    /// Returns the value of the route for exactly `cidr`.
+    pub fn get(&self, cidr: &Cidr) -> Option<&V> {
+        self.map.search(cidr)
+    }
+
+    pub fn get_mut(&mut self, cidr: &Cidr) -> Option<&mut V> {
+        self.map.get_mut(cidr)
+    }
+
+    /// Returns the route with the narrowest network that `addr` is in.
+    pub fn longest_match(&self, addr: IpAddr) -> Option<(Cidr, &V)> {
+        self.covering(&Cidr::host(addr)).last()
+    }
+
+    /// Returns the routes whose networks hold `cidr`, itself included, from the widest to the
+    /// narrowest.
+    pub fn covering(&self, cidr: &Cidr) -> CoveringRoutes<'_, V> {
+        CoveringRoutes {
+            inner: Prefixes::new(&self.map.root, Cow::Owned(cidr.into_encoded())),
+        }
+    }
+
+    /// Returns the routes whose networks are inside `cidr`, itself included, in order.
+    pub fn covered(&self, cidr: &Cidr) -> Routes<'_, V> {
+        Routes {
+            inner: self.map.prefix_iter(cidr).decoded(),
+        }
+    }
+
+    /// Returns the routes in the order of their networks.
+    pub fn iter(&self) -> Routes<'_, V> {
+        Routes {
+            inner: self.map.iter().decoded(),
+        }
+    }
+}
+
+impl<'a, V> Iterator for Routes<'a, V> {
+    type Item = (Cidr, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+impl<'a, V> DoubleEndedIterator for Routes<'a, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back()
+    }
+}
+
+impl<'a, V> Iterator for CoveringRoutes<'a, V> {
+    type Item = (Cidr, &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // the table only ever stores keys encoded from a Cidr
+        let (key, value) = self.inner.next()?;
+        Cidr::decode(key).map(|cidr| (cidr, value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::_lcg;
+    use std::str::FromStr;
+
+    fn _cidr(cidr: &str) -> Cidr {
+        let (addr, prefix_len) = cidr.split_at(cidr.find('/').unwrap());
+        Cidr::new(
+            IpAddr::from_str(addr).unwrap(),
+            prefix_len[1..].parse().unwrap(),
+        )
+        .unwrap()
+    }
+
+    // addresses around a handful of bases, so that the networks nest and overlap
+    fn _addrs(seed: u64, count: usize) -> Vec<IpAddr> {
+        let mut lcg = _lcg(seed);
+        let mut next = move || lcg() >> 16;
+        let bases: [IpAddr; 4] = [
+            IpAddr::from_str("10.0.0.0").unwrap(),
+            IpAddr::from_str("192.168.255.0").unwrap(),
+            IpAddr::from_str("2001:db8::").unwrap(),
+            IpAddr::from_str("fe80::ffff:0:0").unwrap(),
+        ];
+        (0..count)
+            .map(|_| {
+                let (family, bits) = Cidr::bits(bases[next() as usize % bases.len()]);
+                let noise = (next() as u128) << (128 - 48 - next() % 80);
+                Cidr::from_bits(family, bits ^ noise, 0).addr()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_cidr() {
+        let cidr = _cidr("10.1.2.3/19");
+        assert_eq!(cidr.addr(), IpAddr::from_str("10.1.0.0").unwrap());
+        assert_eq!(cidr.prefix_len(), 19);
+        assert_eq!(cidr.to_string(), "10.1.0.0/19");
+        assert!(cidr.contains(IpAddr::from_str("10.1.31.255").unwrap()));
+        assert!(!cidr.contains(IpAddr::from_str("10.1.32.0").unwrap()));
+        assert!(!cidr.contains(IpAddr::from_str("::a01:0").unwrap()));
+        assert!(cidr.covers(&_cidr("10.1.16.0/20")));
+        assert!(!cidr.covers(&_cidr("10.1.0.0/18")));
+        assert_eq!(Cidr::new(IpAddr::from_str("10.0.0.0").unwrap(), 33), None);
+        assert_eq!(
+            _cidr("2001:db8:abcd::1/45").to_string(),
+            "2001:db8:abc8::/45"
+        );
+        assert_eq!(_cidr("::/0").encode().len(), 1);
+
+        let cidrs = [
+            _cidr("0.0.0.0/0"),
+            _cidr("10.0.0.0/8"),
+            _cidr("10.0.0.0/9"),
+            _cidr("10.128.0.0/9"),
+            _cidr("255.255.255.255/32"),
+            _cidr("::/0"),
+            _cidr("2001:db8::/32"),
+            _cidr("2001:db8::1/128"),
+        ];
+        for (one, two) in cidrs.iter().zip(cidrs.iter().skip(1)) {
+            assert!(one < two && one.encode() < two.encode());
+            let (mut nested_one, mut nested_two) = (Vec::new(), Vec::new());
+            (*one, 1u8).encode_into(&mut nested_one);
+            (*two, 0u8).encode_into(&mut nested_two);
+            assert!(nested_one < nested_two);
+        }
+        for cidr in cidrs.iter() {
+            assert_eq!(Cidr::decode(&cidr.encode()), Some(*cidr));
+            let nested = (*cidr, 7u8).encode().into_owned();
+            assert_eq!(<(Cidr, u8)>::decode(&nested), Some((*cidr, 7)));
+        }
+        assert_eq!(Cidr::decode(&[V4, 0, 2]), None);
+        assert_eq!(Cidr::decode(&[5, 0]), None);
+        assert_eq!(Cidr::decode(&[V4; 34]), None);
+    }
+
+    fn _lookup(table: &IpRoutingTable<usize>, addr: &str) -> Option<String> {
+        table
+            .longest_match(IpAddr::from_str(addr).unwrap())
+            .map(|(cidr, _)| cidr.to_string())
+    }
+
+    #[test]
+    fn test_longest_match() {
+        let mut table = IpRoutingTable::new();
+        for (idx, cidr) in [
+            "0.0.0.0/0",
+            "10.0.0.0/8",
+            "10.1.0.0/19",
+            "10.1.16.0/20",
+            "::/0",
+        ]
+        .iter()
+        .enumerate()
+        {
+            table.insert(_cidr(cidr), idx);
+        }
+        assert_eq!(
+            _lookup(&table, "10.1.17.1").as_deref(),
+            Some("10.1.16.0/20")
+        );
+        assert_eq!(_lookup(&table, "10.1.15.1").as_deref(), Some("10.1.0.0/19"));
+        assert_eq!(_lookup(&table, "10.1.32.1").as_deref(), Some("10.0.0.0/8"));
+        assert_eq!(_lookup(&table, "11.0.0.1").as_deref(), Some("0.0.0.0/0"));
+        assert_eq!(_lookup(&table, "::ffff:10.1.17.1").as_deref(), Some("::/0"));
+
+        assert_eq!(table.remove(&_cidr("0.0.0.0/0")), Some(0));
+        assert_eq!(table.remove(&_cidr("10.0.0.0/9")), None);
+        assert_eq!(_lookup(&table, "11.0.0.1"), None);
+        assert_eq!(table.get(&_cidr("10.1.0.0/19")), Some(&2));
+        assert_eq!(table.get(&_cidr("10.1.0.0/18")), None);
+        let covering: Vec<String> = table
+            .covering(&_cidr("10.1.16.128/25"))
+            .map(|(cidr, _)| cidr.to_string())
+            .collect();
+        assert_eq!(covering, vec!["10.0.0.0/8", "10.1.0.0/19", "10.1.16.0/20"]);
+        assert_eq!(table.covered(&_cidr("10.0.0.0/8")).count(), 3);
+        assert_eq!(
+            format!("{:?}", table.covered(&_cidr("10.1.16.0/20")).next()),
+            "Some((Cidr { addr: 10.1.16.0, prefix_len: 20 }, 3))"
+        );
+    }
+
+    #[test]
+    fn test_against_linear_scan() {
+        for seed in 0..8 {
+            let addrs = _addrs(seed, 400);
+            let mut table = IpRoutingTable::new();
+            let mut naive: Vec<(Cidr, usize)> = Vec::new();
+            for (idx, addr) in addrs.iter().enumerate() {
+                let width = if addr.is_ipv4() { 32 } else { 128 };
+                let cidr = Cidr::new(*addr, (idx * 7 % (width + 1)) as u8).unwrap();
+                let replaced = naive.iter().position(|(other, _)| *other == cidr);
+                let old = replaced.map(|position| naive.remove(position).1);
+                naive.push((cidr, idx));
+                assert_eq!(table.insert(cidr, idx), old);
+            }
+            for (idx, addr) in addrs.iter().enumerate().step_by(5) {
+                let width = if addr.is_ipv4() { 32 } else { 128 };
+                let cidr = Cidr::new(*addr, (idx * 7 % (width + 1)) as u8).unwrap();
+                let position = naive.iter().position(|(other, _)| *other == cidr);
+                let old = position.map(|position| naive.remove(position).1);
+                assert_eq!(table.remove(&cidr), old);
+            }
+            naive.sort();
+            assert_eq!(table.len(), naive.len());
+            assert!(table
+                .iter()
+                .eq(naive.iter().map(|(cidr, idx)| (*cidr, idx))));
+
+            for addr in _addrs(seed + 100, 200).into_iter().chain(addrs) {
+                let host = Cidr::host(addr);
+                let covering: Vec<(Cidr, &usize)> = naive
+                    .iter()
+                    .filter(|(cidr, _)| cidr.contains(addr))
+                    .map(|(cidr, idx)| (*cidr, idx))
+                    .collect();
+                assert!(table.covering(&host).eq(covering.iter().cloned()));
+                assert_eq!(table.longest_match(addr), covering.last().cloned());
+
+                for prefix_len in [0u8, 9, 19, 32, 45, 100].iter() {
+                    let cidr = match Cidr::new(addr, *prefix_len) {
+                        Some(cidr) => cidr,
+                        None => continue,
+                    };
+                    let covered = naive
+                        .iter()
+                        .filter(|(other, _)| cidr.covers(other))
+                        .map(|(other, idx)| (*other, idx));
+                    assert!(table.covered(&cidr).eq(covered));
+                    let covering = naive
+                        .iter()
+                        .filter(|(other, _)| other.covers(&cidr))
+                        .map(|(other, idx)| (*other, idx));
+                    assert!(table.covering(&cidr).eq(covering));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,559 @@
+use crate::{
+    Automaton, AutomatonSearch, FuzzySearch, Glob, GlobPattern, GlobToken, Leaf, Levenshtein, Node,
+};
+
+impl<'a, V> FuzzySearch<'a, V> {
+    pub(crate) fn new(root: &'a Node<V>, query: Vec<u8>, max_edits: usize) -> Self {
+        FuzzySearch {
+            inner: AutomatonSearch::new(root, Levenshtein { query, max_edits }),
+        }
+    }
+}
+
+impl<'a, V> Iterator for FuzzySearch<'a, V> {
+    type Item = (&'a [u8], &'a V, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (leaf, row) = self.inner.next_leaf()?;
+        Some((&leaf.key, &leaf.value, row[row.len() - 1]))
+    }
+}
+
+impl Automaton for Levenshtein {
+    type State = Vec<usize>;
+
+    fn start(&self) -> Self::State {
+        (0..=self.query.len()).collect()
+    }
+
+    // the row extended by one more key byte
+    fn accept(&self, row: &Self::State, byte: u8) -> Self::State {
+        let mut next = Vec::with_capacity(row.len());
+        next.push(row[0] + 1);
+        for (idx, query_byte) in self.query.iter().enumerate() {
+            let substitution = row[idx] + (*query_byte != byte) as usize;
+            let edit = substitution.min(row[idx + 1] + 1).min(next[idx] + 1);
+            next.push(edit);
+        }
+        next
+    }
+
+    fn is_match(&self, row: &Self::State) -> bool {
+        row[row.len() - 1] <= self.max_edits
+    }
+
+    // once every prefix of the query is too far off, no key below can come back within the
+    // bound
+    fn can_match(&self, row: &Self::State) -> bool {
+        row.iter().any(|edits| *edits <= self.max_edits)
+    }
+}
+
+impl<'a, V> Glob<'a, V> {
+    pub(crate) fn new(root: &'a Node<V>, pattern: &[u8]) -> Self {
+        let pattern = GlobPattern {
+            tokens: GlobPattern::parse(pattern),
+        };
+        Glob {
+            inner: AutomatonSearch::new(root, pattern),
+        }
+    }
+}
+
+impl<'a, V> Iterator for Glob<'a, V> {
+    type Item = (&'a [u8], &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+}
+
+impl GlobPattern {
+    // a `[` without its `]` and a trailing `\` stand for themselves, like in fnmatch
+    fn parse(pattern: &[u8]) -> Vec<GlobToken> {
+        let mut tokens = Vec::new();
+        let mut idx = 0;
+        while idx < pattern.len() {
+            let token = match pattern[idx] {
+                b'*' => GlobToken::AnyRun,
+                b'?' => GlobToken::AnyByte,
+                b'\\' if idx + 1 < pattern.len() => {
+                    idx += 1;
+                    GlobToken::Byte(pattern[idx])
+                }
+                b'[' => match Self::parse_class(&pattern[idx + 1..]) {
+                    Some((class, len)) => {
+                        idx += len;
+                        class
+                    }
+                    None => GlobToken::Byte(b'['),
+                },
+                byte => GlobToken::Byte(byte),
+            };
+            // a run of stars matches what one star does
+            if !(token == GlobToken::AnyRun && tokens.last() == Some(&GlobToken::AnyRun)) {
+                tokens.push(token);
+            }
+            idx += 1;
+        }
+        tokens
+    }
+
+    // parses the class after a `[`, returning it with the number of bytes it took up to the
+    // closing `]`. A `]` right at the start is a member, as is anything escaped.
+    fn parse_class(pattern: &[u8]) -> Option<(GlobToken, usize)> {
+        let negated = matches!(pattern.first(), Some(b'!') | Some(b'^'));
+        let mut idx = negated as usize;
+        let start = idx;
+        let mut ranges = Vec::new();
+        loop {
+            let low = match *pattern.get(idx)? {
+                b']' if idx > start => {
+                    return Some((GlobToken::Class { negated, ranges }, idx + 1))
+                }
+                b'\\' => {
+                    idx += 1;
+                    *pattern.get(idx)?
+                }
+                byte => byte,
+            };
+            idx += 1;
+            let mut high = low;
+            if pattern.get(idx) == Some(&b'-') && !matches!(pattern.get(idx + 1), Some(b']') | None)
+            {
+                idx += 1;
+                if pattern[idx] == b'\\' {
+                    idx += 1;
+                }
+                high = *pattern.get(idx)?;
+                idx += 1;
+            }
+            ranges.push((low, high));
+        }
+    }
+
+    // adds the positions past every star, which can match nothing
+    fn closure(tokens: &[GlobToken], mut states: Vec<usize>) -> Vec<usize> {
+        let mut idx = 0;
+        while idx < states.len() {
+            let position = states[idx];
+            if tokens.get(position) == Some(&GlobToken::AnyRun) {
+                states.push(position + 1);
+            }
+            idx += 1;
+        }
+        states.sort_unstable();
+        states.dedup();
+        states
+    }
+}
+
+impl Automaton for GlobPattern {
+    type State = Vec<usize>;
+
+    fn start(&self) -> Self::State {
+        Self::closure(&self.tokens, vec![0])
+    }
+
+    fn accept(&self, states: &Self::State, byte: u8) -> Self::State {
+        let mut next = Vec::new();
+        for position in states {
+            match self.tokens.get(*position) {
+                Some(GlobToken::Byte(expected)) if *expected == byte => next.push(position + 1),
+                Some(GlobToken::AnyByte) => next.push(position + 1),
+                Some(GlobToken::AnyRun) => next.push(*position),
+                Some(GlobToken::Class { negated, ranges }) => {
+                    let member = ranges
+                        .iter()
+                        .any(|(low, high)| (*low..=*high).contains(&byte));
+                    if member != *negated {
+                        next.push(position + 1);
+                    }
+                }
+                _ => {}
+            }
+        }
+        Self::closure(&self.tokens, next)
+    }
+
+    fn is_match(&self, states: &Self::State) -> bool {
+        states.last() == Some(&self.tokens.len())
+    }
+
+    fn can_match(&self, states: &Self::State) -> bool {
+        !states.is_empty()
+    }
+
+    // the bytes the pattern can go on with, unless a wildcard or a negated class lets it go on
+    // with nearly any of them
+    fn next_bytes(&self, states: &Self::State) -> Option<Vec<u8>> {
+        let mut bytes = Vec::new();
+        for position in states {
+            match self.tokens.get(*position) {
+                Some(GlobToken::Byte(byte)) => bytes.push(*byte),
+                Some(GlobToken::Class {
+                    negated: false,
+                    ranges,
+                }) => {
+                    for (low, high) in ranges {
+                        bytes.extend(*low..=*high);
+                    }
+                }
+                Some(_) => return None,
+                None => {}
+            }
+        }
+        bytes.sort_unstable();
+        bytes.dedup();
+        Some(bytes)
+    }
+}
+
+impl<'a, A: Automaton, V> AutomatonSearch<'a, A, V> {
+    pub(crate) fn new(root: &'a Node<V>, automaton: A) -> Self {
+        let state = automaton.start();
+        AutomatonSearch {
+            automaton,
+            stack: vec![(root, 0, state)],
+        }
+    }
+
+    // feeds a run of key bytes to the automaton, giving up as soon as it can't match anymore
+    fn accept_all(&self, mut state: A::State, bytes: &[u8]) -> Option<A::State> {
+        for byte in bytes {
+            if !self.automaton.can_match(&state) {
+                return None;
+            }
+            state = self.automaton.accept(&state, *byte);
+        }
+        if self.automaton.can_match(&state) {
+            Some(state)
+        } else {
+            None
+        }
+    }
+
+    fn push_child(&mut self, child: &'a Node<V>, depth: usize, state: &A::State, key_char: u8) {
+        if let Some(state) = self.accept_all(state.clone(), &[key_char]) {
+            self.stack.push((child, depth, state));
+        }
+    }
+
+    // the next leaf the automaton accepts along with the state it ends in
+    pub(crate) fn next_leaf(&mut self) -> Option<(&'a Leaf<V>, A::State)> {
+        while let Some((node, depth, state)) = self.stack.pop() {
+            match node {
+                Node::None => {}
+                Node::Leaf(leaf) => match self.accept_all(state, &leaf.key[depth..]) {
+                    Some(state) if self.automaton.is_match(&state) => return Some((leaf, state)),
+                    _ => {}
+                },
+                node => {
+                    // the whole compressed path is fed to the automaton before any child is
+                    // looked at, a run it can't match prunes the node at once
+                    let prefix = node.full_prefix(depth);
+                    let state = match self.accept_all(state, prefix) {
+                        Some(state) => state,
+                        None => continue,
+                    };
+                    let end = depth + prefix.len();
+                    // the children go on the stack last to first so that they come off it in
+                    // key order. Those for the bytes the automaton can take next are looked up
+                    // directly, which the child tables of node48 and node256 answer at once,
+                    // instead of walking every child.
+                    match self.automaton.next_bytes(&state) {
+                        Some(bytes) => {
+                            for byte in bytes.into_iter().rev() {
+                                if let Some(child) = node.child_at(byte) {
+                                    self.push_child(child, end + 1, &state, byte);
+                                }
+                            }
+                        }
+                        None => {
+                            let mut before = usize::MAX;
+                            while let Some((position, child)) = node.prev_entry(before) {
+                                let key_char = match node.key_char_at(position) {
+                                    Some(key_char) => key_char,
+                                    None => break,
+                                };
+                                self.push_child(child, end + 1, &state, key_char);
+                                before = position;
+                            }
+                        }
+                    }
+                    if let Some(Node::Leaf(leaf)) = node.term_leaf() {
+                        if self.automaton.is_match(&state) {
+                            return Some((leaf, state));
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<'a, A: Automaton, V> Iterator for AutomatonSearch<'a, A, V> {
+    type Item = (&'a [u8], &'a V);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (leaf, _) = self.next_leaf()?;
+        Some((&leaf.key, &leaf.value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Art, Automaton, GlobPattern, GlobToken};
+    use std::collections::BTreeMap;
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+
+    fn _levenshtein(one: &[u8], two: &[u8]) -> usize {
+        let mut row: Vec<usize> = (0..=two.len()).collect();
+        for (idx, byte) in one.iter().enumerate() {
+            let mut next = vec![idx + 1];
+            for (jdx, other) in two.iter().enumerate() {
+                let edit = (row[jdx] + (byte != other) as usize)
+                    .min(row[jdx + 1] + 1)
+                    .min(next[jdx] + 1);
+                next.push(edit);
+            }
+            row = next;
+        }
+        row[two.len()]
+    }
+
+    #[test]
+    fn test_fuzzy_search() {
+        let mut art = Art::new();
+        for word in ["", "a", "ab", "abc", "abd", "abcdefghijklmnop", "b", "xyz"].iter() {
+            art.insert(word.to_string(), word.len());
+        }
+        let found: Vec<(&[u8], usize)> = art
+            .fuzzy_search("abc", 1)
+            .map(|(key, _, distance)| (key, distance))
+            .collect();
+        let expected: Vec<(&[u8], usize)> = vec![(b"ab", 1), (b"abc", 0), (b"abd", 1)];
+        assert_eq!(found, expected);
+        assert_eq!(art.fuzzy_search("", 0).count(), 1);
+        assert_eq!(art.fuzzy_search("", 1).count(), 3);
+        assert_eq!(art.fuzzy_search("abcdefghijklmnop", 0).count(), 1);
+        assert_eq!(art.fuzzy_search("abcdefgXijklmnop", 0).count(), 0);
+        assert_eq!(art.fuzzy_search("abcdefgXijklmnopq", 2).count(), 1);
+        assert_eq!(art.fuzzy_search("zzz", 3).count(), 7);
+    }
+
+    #[test]
+    fn test_fuzzy_search_words() {
+        let reader = BufReader::new(File::open("/usr/share/dict/words").unwrap());
+        let mut art = Art::new();
+        let mut expected = BTreeMap::new();
+        for (idx, line) in reader.lines().map_while(Result::ok).enumerate() {
+            let key = line.trim().as_bytes().to_vec();
+            art.insert(key.clone(), idx);
+            expected.insert(key, idx);
+        }
+
+        let queries: Vec<Vec<u8>> = expected
+            .keys()
+            .step_by(12007)
+            .flat_map(|word| vec![word.clone(), [&word[1..], b"x"].concat()])
+            .collect();
+        for query in queries.iter() {
+            let distances: Vec<(&[u8], &usize, usize)> = expected
+                .iter()
+                .map(|(key, value)| (key.as_slice(), value, _levenshtein(key, query)))
+                .collect();
+            for max_edits in 0..3 {
+                let matching = distances
+                    .iter()
+                    .filter(|(_, _, distance)| *distance <= max_edits)
+                    .cloned();
+                assert!(art.fuzzy_search(query, max_edits).eq(matching));
+            }
+        }
+    }
+
+    #[test]
+    fn test_next_bytes() {
+        // the keys made of digits only, with the children for them looked up directly
+        struct Digits;
+
+        impl Automaton for Digits {
+            type State = bool;
+
+            fn start(&self) -> bool {
+                true
+            }
+
+            fn accept(&self, digits: &bool, byte: u8) -> bool {
+                *digits && byte.is_ascii_digit()
+            }
+
+            fn is_match(&self, digits: &bool) -> bool {
+                *digits
+            }
+
+            fn can_match(&self, digits: &bool) -> bool {
+                *digits
+            }
+
+            fn next_bytes(&self, _digits: &bool) -> Option<Vec<u8>> {
+                Some((b'0'..=b'9').collect())
+            }
+        }
+
+        // a node256 at the root and below 7, a node4 below 1
+        let mut art = Art::new();
+        for byte in 0..=255u8 {
+            art.insert(vec![byte], ());
+            art.insert(vec![b'7', byte, b'0'], ());
+        }
+        for key in ["", "1", "12", "1x", "19", "7"].iter() {
+            art.insert(key.as_bytes().to_vec(), ());
+        }
+        let digits = |key: &[u8]| key.iter().all(u8::is_ascii_digit);
+        let expected: Vec<&[u8]> = art.keys().filter(|key| digits(key)).collect();
+        let found: Vec<&[u8]> = art.search_automaton(Digits).map(|(k, _)| k).collect();
+        assert_eq!(found, expected);
+        assert_eq!(found.len(), 1 + 10 + 2 + 10);
+    }
+
+    // backtracking over the parsed pattern, the way the traversal doesn't
+    fn _glob_matches(tokens: &[GlobToken], key: &[u8]) -> bool {
+        match tokens.split_first() {
+            None => key.is_empty(),
+            Some((GlobToken::AnyRun, rest)) => {
+                (0..=key.len()).any(|skip| _glob_matches(rest, &key[skip..]))
+            }
+            Some((token, rest)) => match key.split_first() {
+                None => false,
+                Some((byte, key)) => {
+                    let matches = match token {
+                        GlobToken::Byte(expected) => expected == byte,
+                        GlobToken::Class { negated, ranges } => {
+                            ranges.iter().any(|(low, high)| low <= byte && byte <= high) != *negated
+                        }
+                        _ => true,
+                    };
+                    matches && _glob_matches(rest, key)
+                }
+            },
+        }
+    }
+
+    #[test]
+    fn test_glob_parse() {
+        use GlobToken::*;
+        let parse = GlobPattern::parse;
+        assert_eq!(parse(b"a**?"), vec![Byte(b'a'), AnyRun, AnyByte]);
+        assert_eq!(parse(br"\*\\\"), vec![Byte(b'*'), Byte(b'\\'), Byte(b'\\')]);
+        assert_eq!(
+            parse(b"[]a-c][!x][^-]"),
+            vec![
+                Class {
+                    negated: false,
+                    ranges: vec![(b']', b']'), (b'a', b'c')]
+                },
+                Class {
+                    negated: true,
+                    ranges: vec![(b'x', b'x')]
+                },
+                Class {
+                    negated: true,
+                    ranges: vec![(b'-', b'-')]
+                },
+            ]
+        );
+        assert_eq!(
+            parse(br"[a-][\]-\^]"),
+            vec![
+                Class {
+                    negated: false,
+                    ranges: vec![(b'a', b'a'), (b'-', b'-')]
+                },
+                Class {
+                    negated: false,
+                    ranges: vec![(b']', b'^')]
+                },
+            ]
+        );
+        assert_eq!(
+            parse(b"a[bc"),
+            vec![Byte(b'a'), Byte(b'['), Byte(b'b'), Byte(b'c')]
+        );
+    }
+
+    #[test]
+    fn test_glob() {
+        let keys = [
+            "",
+            "Zed",
+            "a*b",
+            "a[b",
+            "a\\b",
+            "log-2026-01-03",
+            "log-2026-02-x",
+            "log-2026-10-01",
+            "user:1:profile",
+            "user:1:session",
+            "user:22:session",
+            "user:22:x:session",
+        ];
+        let art: Art<String, ()> = keys.iter().map(|key| (key.to_string(), ())).collect();
+        let glob = |pattern: &str| -> Vec<&[u8]> { art.glob(pattern).map(|(k, _)| k).collect() };
+        assert_eq!(
+            glob("user:*:session"),
+            vec![
+                &b"user:1:session"[..],
+                b"user:22:session",
+                b"user:22:x:session"
+            ]
+        );
+        assert_eq!(
+            glob("log-2026-0?-*"),
+            vec![&b"log-2026-01-03"[..], b"log-2026-02-x"]
+        );
+        assert_eq!(glob(r"a\*b"), vec![&b"a*b"[..]]);
+        assert_eq!(glob("a*b").len(), 3);
+        assert_eq!(glob("a[b"), vec![&b"a[b"[..]]);
+        assert_eq!(glob(r"a[[\\]b"), vec![&b"a[b"[..], b"a\\b"]);
+        assert_eq!(glob("[!a-z]*"), vec![&b"Zed"[..]]);
+        assert_eq!(glob(""), vec![&b""[..]]);
+        assert_eq!(glob("*").len(), keys.len());
+        assert_eq!(glob("user:1:session?").len(), 0);
+        assert_eq!(glob("*:session").len(), 3);
+    }
+
+    #[test]
+    fn test_glob_words() {
+        let reader = BufReader::new(File::open("/usr/share/dict/words").unwrap());
+        let art: Art<Vec<u8>, usize> = reader
+            .lines()
+            .map_while(Result::ok)
+            .enumerate()
+            .map(|(idx, line)| (line.trim().as_bytes().to_vec(), idx))
+            .collect();
+
+        let patterns = [
+            "a*",
+            "*ing",
+            "?a?e*",
+            "[a-c]*[!s]",
+            "*q*u*",
+            "[^aeiou][aeiou]*",
+            "b??",
+            "*[xz]",
+            "ab*c*d",
+            "*a*e*i*o*",
+            "[m-p][!a-t]?*",
+        ];
+        for pattern in patterns.iter() {
+            let tokens = GlobPattern::parse(pattern.as_bytes());
+            let matching = art.iter().filter(|(key, _)| _glob_matches(&tokens, key));
+            assert!(art.glob(pattern).eq(matching), "{}", pattern);
+        }
+        assert!(art.glob("*").eq(art.iter()));
+    }
+}
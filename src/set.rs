@@ -0,0 +1,619 @@
+use crate::{
+    Art, ArtKey, ArtSet, DecodedKeys, Difference, Intersection, KeyLookup, KeyPrefix, Keys,
+    Lockstep, Node, Path, SymmetricDifference, Union,
+};
+use std::cmp::Ordering;
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::ops::RangeBounds;
+
+impl<K: ArtKey> Default for ArtSet<K> {
+    fn default() -> Self {
+        ArtSet::new()
+    }
+}
+
+impl<K: ArtKey> PartialEq for ArtSet<K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.map == other.map
+    }
+}
+
+impl<K: ArtKey> Eq for ArtSet<K> {}
+
+impl<K: ArtKey> PartialOrd for ArtSet<K> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<K: ArtKey> Ord for ArtSet<K> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.map.cmp(&other.map)
+    }
+}
+
+impl<K: ArtKey> Hash for ArtSet<K> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.map.hash(state)
+    }
+}
+
+/// Prints the keys as a set, decoded back into `K` where they can be.
+impl<K: ArtKey + Debug> Debug for ArtSet<K> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut set = f.debug_set();
+        for key in self.iter() {
+            match K::decode(key) {
+                Some(decoded) => set.entry(&decoded),
+                None => set.entry(&key),
+            };
+        }
+        set.finish()
+    }
+}
+
+impl<K: ArtKey> ArtSet<K> {
+    pub fn new() -> Self {
+        ArtSet { map: Art::new() }
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Adds a key to the set, returning `false` if it was there already.
+    pub fn insert(&mut self, key: K) -> bool {
+        self.map.insert(key, ()).is_none()
+    }
+
+    /// Returns `true` if the set holds `key`.
+    pub fn contains<Q: KeyLookup<K> + ?Sized>(&self, key: &Q) -> bool {
+        self.map.contains_key(key)
+    }
+
+    /// Removes a key from the set, returning `true` if it was there.
+    pub fn remove<Q: KeyLookup<K> + ?Sized>(&mut self, key: &Q) -> bool {
+        self.map.remove(key).is_some()
+    }
+
+    /// Returns an iterator over the encoded keys in order, which `decoded` turns back into `K`.
+    pub fn iter(&self) -> Keys<'_, K, ()> {
+        self.map.keys()
+    }
+
+    /// Returns an iterator over the keys whose encoding starts with the encoding of `prefix`,
+    /// in order.
+    pub fn prefix_iter<Q: KeyPrefix<K> + ?Sized>(&self, prefix: &Q) -> Keys<'_, K, ()> {
+        Keys {
+            inner: self.map.prefix_iter(prefix),
+        }
+    }
+
+    /// Returns an iterator over the keys that fall in `range`, in order.
+    pub fn range<Q, R>(&self, range: R) -> Keys<'_, K, ()>
+    where
+        Q: KeyLookup<K> + ?Sized,
+        R: RangeBounds<Q>,
+    {
+        Keys {
+            inner: self.map.range(range),
+        }
+    }
+
+    /// Returns the keys in `self` or `other`, in order.
+    pub fn union<'a>(&'a self, other: &'a ArtSet<K>) -> Union<'a, K, (), ()> {
+        self.map.union(&other.map)
+    }
+
+    /// Returns the keys in both `self` and `other`, in order.
+    pub fn intersection<'a>(&'a self, other: &'a ArtSet<K>) -> Intersection<'a, K, (), ()> {
+        self.map.intersection(&other.map)
+    }
+
+    /// Returns the keys in `self` but not in `other`, in order.
+    pub fn difference<'a>(&'a self, other: &'a ArtSet<K>) -> Difference<'a, K, (), ()> {
+        self.map.difference(&other.map)
+    }
+
+    /// Returns the keys in exactly one of `self` and `other`, in order.
+    pub fn symmetric_difference<'a>(
+        &'a self,
+        other: &'a ArtSet<K>,
+    ) -> SymmetricDifference<'a, K, (), ()> {
+        self.map.symmetric_difference(&other.map)
+    }
+
+    /// Returns `true` if every key of `self` is in `other`.
+    pub fn is_subset(&self, other: &ArtSet<K>) -> bool {
+        self.len() <= other.len() && self.difference(other).next().is_none()
+    }
+
+    /// Returns `true` if `self` and `other` have no key in common.
+    pub fn is_disjoint(&self, other: &ArtSet<K>) -> bool {
+        self.intersection(other).next().is_none()
+    }
+}
+
+impl<'a, K, V, W> Lockstep<'a, K, V, W> {
+    pub(crate) fn new(a_root: &'a Node<V>, b_root: &'a Node<W>) -> Self {
+        let mut a = Path::new();
+        a.first(a_root);
+        let mut b = Path::new();
+        b.first(b_root);
+        Lockstep {
+            a_root,
+            a,
+            b_root,
+            b,
+            #[cfg(test)]
+            steps: 0,
+            marker: PhantomData,
+        }
+    }
+
+    fn keys(&mut self) -> (Option<&'a [u8]>, Option<&'a [u8]>) {
+        #[cfg(test)]
+        {
+            self.steps += 1;
+        }
+        (
+            self.a.leaf().map(|leaf| leaf.key.as_slice()),
+            self.b.leaf().map(|leaf| leaf.key.as_slice()),
+        )
+    }
+
+    // move the first tree on to key, skipping the subtrees whose compressed paths diverge
+    // from it before they are reached
+    fn seek_a(&mut self, key: &[u8]) {
+        self.a.seek_ahead(self.a_root, key, true);
+    }
+
+    fn seek_b(&mut self, key: &[u8]) {
+        self.b.seek_ahead(self.b_root, key, true);
+    }
+}
+
+impl<'a, K: ArtKey, V, W> Union<'a, K, V, W> {
+    /// Decodes the keys handed out back into `K`.
+    pub fn decoded(self) -> DecodedKeys<Self, K> {
+        DecodedKeys::new(self)
+    }
+}
+
+impl<'a, K: ArtKey, V, W> Intersection<'a, K, V, W> {
+    /// Decodes the keys handed out back into `K`.
+    pub fn decoded(self) -> DecodedKeys<Self, K> {
+        DecodedKeys::new(self)
+    }
+}
+
+impl<'a, K: ArtKey, V, W> Difference<'a, K, V, W> {
+    /// Decodes the keys handed out back into `K`.
+    pub fn decoded(self) -> DecodedKeys<Self, K> {
+        DecodedKeys::new(self)
+    }
+}
+
+impl<'a, K: ArtKey, V, W> SymmetricDifference<'a, K, V, W> {
+    /// Decodes the keys handed out back into `K`.
+    pub fn decoded(self) -> DecodedKeys<Self, K> {
+        DecodedKeys::new(self)
+    }
+}
+
+impl<'a, K, V, W> Iterator for Union<'a, K, V, W> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let inner = &mut self.inner;
+        match inner.keys() {
+            (Some(a), Some(b)) => match a.cmp(b) {
+                Ordering::Less => {
+                    inner.a.next();
+                    Some(a)
+                }
+                Ordering::Greater => {
+                    inner.b.next();
+                    Some(b)
+                }
+                Ordering::Equal => {
+                    inner.a.next();
+                    inner.b.next();
+                    Some(a)
+                }
+            },
+            (Some(a), None) => {
+                inner.a.next();
+                Some(a)
+            }
+            (None, Some(b)) => {
+                inner.b.next();
+                Some(b)
+            }
+            (None, None) => None,
+        }
+    }
+}
+
+impl<'a, K, V, W> Iterator for Intersection<'a, K, V, W> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let inner = &mut self.inner;
+        loop {
+            let (a, b) = inner.keys();
+            let (a, b) = (a?, b?);
+            match a.cmp(b) {
+                Ordering::Less => inner.seek_a(b),
+                Ordering::Greater => inner.seek_b(a),
+                Ordering::Equal => {
+                    inner.a.next();
+                    inner.b.next();
+                    return Some(a);
+                }
+            }
+        }
+    }
+}
+
+impl<'a, K, V, W> Iterator for Difference<'a, K, V, W> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let inner = &mut self.inner;
+        loop {
+            let (a, b) = inner.keys();
+            let a = a?;
+            match b.map(|b| a.cmp(b)) {
+                None | Some(Ordering::Less) => {
+                    inner.a.next();
+                    return Some(a);
+                }
+                Some(Ordering::Greater) => inner.seek_b(a),
+                Some(Ordering::Equal) => {
+                    inner.a.next();
+                    inner.b.next();
+                }
+            }
+        }
+    }
+}
+
+impl<'a, K, V, W> Iterator for SymmetricDifference<'a, K, V, W> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let inner = &mut self.inner;
+        loop {
+            match inner.keys() {
+                (Some(a), Some(b)) => match a.cmp(b) {
+                    Ordering::Less => {
+                        inner.a.next();
+                        return Some(a);
+                    }
+                    Ordering::Greater => {
+                        inner.b.next();
+                        return Some(b);
+                    }
+                    Ordering::Equal => {
+                        inner.a.next();
+                        inner.b.next();
+                    }
+                },
+                (Some(a), None) => {
+                    inner.a.next();
+                    return Some(a);
+                }
+                (None, Some(b)) => {
+                    inner.b.next();
+                    return Some(b);
+                }
+                (None, None) => return None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tests::_lcg;
+    use crate::{Art, ArtSet, Leaf};
+    use std::collections::BTreeSet;
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+    use std::mem::size_of;
+
+    fn _words() -> Vec<String> {
+        let reader = BufReader::new(File::open("/usr/share/dict/words").unwrap());
+        reader
+            .lines()
+            .map_while(Result::ok)
+            .map(|line| line.trim().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_insert_contains_remove() {
+        assert_eq!(size_of::<Leaf<()>>(), size_of::<Vec<u8>>());
+        let mut set = ArtSet::new();
+        let mut expected = BTreeSet::new();
+        for word in _words().into_iter().step_by(3) {
+            assert_eq!(set.insert(word.clone()), expected.insert(word));
+        }
+        assert!(!set.insert(expected.iter().next().unwrap().clone()));
+        assert_eq!(set.len(), expected.len());
+        assert!(set.iter().eq(expected.iter().map(|w| w.as_bytes())));
+        assert!(set
+            .iter()
+            .rev()
+            .eq(expected.iter().rev().map(|w| w.as_bytes())));
+
+        for word in _words().iter().step_by(2) {
+            assert_eq!(set.contains(word.as_str()), expected.contains(word));
+            assert_eq!(set.remove(word.as_bytes()), expected.remove(word));
+        }
+        assert_eq!(set.len(), expected.len());
+        assert!(set.iter().eq(expected.iter().map(|w| w.as_bytes())));
+    }
+
+    #[test]
+    fn test_prefix_and_range() {
+        let mut set = ArtSet::new();
+        for word in ["A", "AMD", "AMDs", "Ab", "Cone", "Dong"].iter() {
+            set.insert(word.to_string());
+        }
+        let scanned: Vec<&[u8]> = set.prefix_iter("AM").collect();
+        assert_eq!(scanned, vec![&b"AMD"[..], b"AMDs"]);
+        let scanned: Vec<&[u8]> = set.range("AMD".to_string().."Cone".to_string()).collect();
+        assert_eq!(scanned, vec![&b"AMD"[..], b"AMDs", b"Ab"]);
+
+        let mut numbers = ArtSet::new();
+        for n in -5i32..5 {
+            numbers.insert(n);
+        }
+        assert_eq!(numbers.range(-2..).count(), 7);
+        assert!(numbers.contains(&-5i32));
+    }
+
+    #[test]
+    fn test_comparisons_and_debug() {
+        let (mut a, mut b) = (ArtSet::new(), ArtSet::new());
+        for n in [3i32, -1, 7].iter() {
+            a.insert(*n);
+        }
+        for n in [7i32, 3, -1].iter() {
+            b.insert(*n);
+        }
+        assert_eq!(a, b);
+        assert_eq!(format!("{:?}", a), "{-1, 3, 7}");
+        b.insert(8);
+        assert!(a < b);
+        b.remove(&-1);
+        assert!(a < b && b.len() == a.len());
+        assert_eq!(format!("{:?}", ArtSet::<String>::new()), "{}");
+    }
+
+    #[test]
+    fn test_set_algebra() {
+        let words = _words();
+        let (mut a, mut b) = (ArtSet::new(), ArtSet::new());
+        let (mut expected_a, mut expected_b) = (BTreeSet::new(), BTreeSet::new());
+        for (idx, word) in words.iter().enumerate() {
+            if idx % 2 == 0 {
+                a.insert(word.as_bytes().to_vec());
+                expected_a.insert(word.as_bytes().to_vec());
+            }
+            if idx % 3 == 0 {
+                b.insert(word.as_bytes().to_vec());
+                expected_b.insert(word.as_bytes().to_vec());
+            }
+        }
+
+        let as_slices =
+            |keys: Vec<&Vec<u8>>| -> Vec<Vec<u8>> { keys.into_iter().cloned().collect() };
+        let collect =
+            |keys: Vec<&[u8]>| -> Vec<Vec<u8>> { keys.into_iter().map(|k| k.to_vec()).collect() };
+        assert_eq!(
+            collect(a.union(&b).collect()),
+            as_slices(expected_a.union(&expected_b).collect())
+        );
+        assert_eq!(
+            collect(a.intersection(&b).collect()),
+            as_slices(expected_a.intersection(&expected_b).collect())
+        );
+        assert_eq!(
+            collect(b.difference(&a).collect()),
+            as_slices(expected_b.difference(&expected_a).collect())
+        );
+        assert_eq!(
+            collect(a.symmetric_difference(&b).collect()),
+            as_slices(expected_a.symmetric_difference(&expected_b).collect())
+        );
+        assert!(!a.is_disjoint(&b));
+        assert!(!b.is_subset(&a));
+
+        let empty = ArtSet::new();
+        assert!(empty.is_subset(&a));
+        assert!(empty.is_disjoint(&a));
+        assert_eq!(a.union(&empty).count(), a.len());
+        assert_eq!(empty.difference(&a).count(), 0);
+        let evens: ArtSet<Vec<u8>> = {
+            let mut set = ArtSet::new();
+            for key in a.intersection(&b) {
+                set.insert(key.to_vec());
+            }
+            set
+        };
+        assert!(evens.is_subset(&a) && evens.is_subset(&b));
+    }
+
+    #[test]
+    fn test_decoded_keys() {
+        let (mut a, mut b) = (ArtSet::new(), ArtSet::new());
+        let (mut expected_a, mut expected_b) = (BTreeSet::new(), BTreeSet::new());
+        for (idx, word) in _words().into_iter().step_by(7).enumerate() {
+            let key = (word, idx as u32 % 5);
+            if idx % 2 == 0 {
+                a.insert(key.clone());
+                expected_a.insert(key.clone());
+            }
+            if idx % 3 == 0 {
+                b.insert(key.clone());
+                expected_b.insert(key);
+            }
+        }
+
+        assert!(a.iter().decoded().eq(expected_a.iter().cloned()));
+        assert!(a
+            .iter()
+            .decoded()
+            .rev()
+            .eq(expected_a.iter().rev().cloned()));
+        let (low, high) = (("m".to_string(), 0), ("p".to_string(), 0));
+        assert!(a
+            .range(low.clone()..high.clone())
+            .decoded()
+            .eq(expected_a.range(low..high).cloned()));
+        assert!(a
+            .union(&b)
+            .decoded()
+            .eq(expected_a.union(&expected_b).cloned()));
+        assert!(a
+            .intersection(&b)
+            .decoded()
+            .eq(expected_a.intersection(&expected_b).cloned()));
+        assert!(a
+            .difference(&b)
+            .decoded()
+            .eq(expected_a.difference(&expected_b).cloned()));
+        assert!(a
+            .symmetric_difference(&b)
+            .decoded()
+            .eq(expected_a.symmetric_difference(&expected_b).cloned()));
+
+        let mut numbers = ArtSet::new();
+        for n in [0u64, 1 << 40, 255, 256, u64::MAX] {
+            numbers.insert(n);
+        }
+        let decoded: Vec<u64> = numbers.prefix_iter(&0u64).decoded().collect();
+        assert_eq!(decoded, [0]);
+        let decoded: Vec<u64> = numbers.iter().decoded().collect();
+        assert_eq!(decoded, [0, 255, 256, 1 << 40, u64::MAX]);
+    }
+
+    #[test]
+    fn test_lockstep_steps_over_disjoint_prefixes() {
+        // the trees take turns owning the prefixes, so neither has a key under the other's
+        let (mut a, mut b) = (ArtSet::new(), ArtSet::new());
+        for group in 0..20 {
+            let set = if group % 2 == 0 { &mut a } else { &mut b };
+            for i in 0..500 {
+                set.insert(format!("{:02}/{}", group, i).into_bytes());
+            }
+        }
+        b.insert(b"04/499".to_vec());
+        a.insert(b"17/0".to_vec());
+
+        let mut common = a.intersection(&b);
+        let found: Vec<&[u8]> = common.by_ref().collect();
+        assert_eq!(found, [&b"04/499"[..], &b"17/0"[..]]);
+        // a merge would step through all 10002 leaves, the seeks only land once or twice in
+        // each prefix
+        let steps = common.inner.steps;
+        assert!(steps <= 2 * 20 + 4, "{} steps", steps);
+
+        let mut unique = a.difference(&b);
+        assert_eq!(unique.by_ref().count(), a.len() - 2);
+        assert!(unique.inner.steps <= a.len() + 20 + 2);
+    }
+
+    // keys over a small alphabet, sharing long prefixes with each other
+    fn _keys(seed: u64, count: usize) -> Vec<Vec<u8>> {
+        let mut lcg = _lcg(seed);
+        let mut next = move || (lcg() >> 33) as usize;
+        let stems: [&[u8]; 3] = [b"", b"common/prefix/longer/than/a/partial/", b"ab"];
+        (0..count)
+            .map(|_| {
+                let mut key = stems[next() % stems.len()].to_vec();
+                for _ in 0..next() % 6 {
+                    key.push(b"ab\0c"[next() % 4]);
+                }
+                key
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_lockstep_between_maps() {
+        for seed in 0..20 {
+            let mut a: Art<Vec<u8>, usize> = Art::new();
+            let mut b: Art<Vec<u8>, String> = Art::new();
+            let mut expected_a = BTreeSet::new();
+            let mut expected_b = BTreeSet::new();
+            for (idx, key) in _keys(seed, 40 + seed as usize * 20).into_iter().enumerate() {
+                if idx % 3 != 0 {
+                    a.insert(key.clone(), idx);
+                    expected_a.insert(key.clone());
+                }
+                if idx % 2 == 0 || seed % 4 == 0 {
+                    b.insert(key.clone(), idx.to_string());
+                    expected_b.insert(key);
+                }
+            }
+
+            let expected =
+                |keys: Vec<&Vec<u8>>| -> Vec<Vec<u8>> { keys.into_iter().cloned().collect() };
+            let collect = |keys: Vec<&[u8]>| -> Vec<Vec<u8>> {
+                keys.into_iter().map(|k| k.to_vec()).collect()
+            };
+            assert_eq!(
+                collect(a.union(&b).collect()),
+                expected(expected_a.union(&expected_b).collect())
+            );
+            assert_eq!(
+                collect(a.intersection(&b).collect()),
+                expected(expected_a.intersection(&expected_b).collect())
+            );
+            assert_eq!(
+                collect(b.intersection(&a).collect()),
+                expected(expected_a.intersection(&expected_b).collect())
+            );
+            assert_eq!(
+                collect(a.difference(&b).collect()),
+                expected(expected_a.difference(&expected_b).collect())
+            );
+            assert_eq!(
+                collect(b.difference(&a).collect()),
+                expected(expected_b.difference(&expected_a).collect())
+            );
+            assert_eq!(
+                collect(b.symmetric_difference(&a).collect()),
+                expected(expected_b.symmetric_difference(&expected_a).collect())
+            );
+        }
+    }
+
+    #[test]
+    fn test_lockstep_disjoint_subtrees() {
+        // one tree's keys all sit in subtrees the other tree branches away from
+        let (mut a, mut b) = (ArtSet::new(), ArtSet::new());
+        for i in 0u32..2000 {
+            a.insert((b"left/".to_vec(), i));
+            b.insert((b"right/".to_vec(), i));
+        }
+        b.insert((b"left/".to_vec(), 1999));
+        a.insert((b"right/".to_vec(), 0));
+        let common: Vec<&[u8]> = a.intersection(&b).collect();
+        assert_eq!(common.len(), 2);
+        assert_eq!(a.difference(&b).count(), 1999);
+        assert_eq!(b.difference(&a).count(), 1999);
+        assert_eq!(a.union(&b).count(), 4000);
+        assert_eq!(a.symmetric_difference(&b).count(), 3998);
+    }
+}